@@ -0,0 +1,378 @@
+//! Caching of dereferenced objects, so that e.g. the actor of every incoming
+//! activity isn't fetched again for each one.
+//!
+//! A [Cache] wraps a [Fetcher] and keeps the parsed documents it fetched by
+//! id, for as long as the server's `Cache-Control` allows or else a default
+//! time to live. Stale documents with an `ETag` are revalidated rather than
+//! fetched again, and objects that are gone (404 or 410) are remembered as
+//! [tombstones](Cached::Gone) for a while.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use http::StatusCode;
+use serde_json::Value;
+
+use crate::core::actor::Actor;
+use crate::core::{Document, Object};
+use crate::fetch::{Fetched, Fetcher};
+use crate::{Error, Result};
+
+/// A cached object.
+#[derive(Debug, Clone)]
+pub enum Cached {
+    Actor(Box<Document<Actor>>),
+    Object(Box<Document<Object>>),
+    /// The object is gone: fetching it answered 404 or 410.
+    Gone(StatusCode),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Actor,
+    Object,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Cached,
+    etag: Option<String>,
+    fetched: Instant,
+    expires: Instant,
+}
+
+///////////////////////////
+// Cache
+///////////////////////////
+#[derive(Debug)]
+pub struct Cache<F> {
+    pub fetcher: F,
+    /// How long to keep a document whose response doesn't say.
+    pub default_ttl: Duration,
+    /// The longest to keep a document, whatever its response says.
+    pub max_ttl: Duration,
+    /// How long to remember that an object is gone.
+    pub tombstone_ttl: Duration,
+    /// How many objects to keep. Beyond this, those closest to expiring are
+    /// dropped first.
+    pub max_entries: usize,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl<F: Fetcher> Cache<F> {
+    pub fn new(fetcher: F) -> Self {
+        Cache {
+            fetcher,
+            default_ttl: Duration::from_secs(60 * 60),
+            max_ttl: Duration::from_secs(24 * 60 * 60),
+            tombstone_ttl: Duration::from_secs(24 * 60 * 60),
+            max_entries: 10_000,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// The actor with the id `id`, fetched unless it's cached.
+    pub async fn actor(&self, id: &str) -> Result<Document<Actor>> {
+        match self.get(id, Kind::Actor).await? {
+            Cached::Actor(actor) => Ok(*actor),
+            Cached::Object(object) => convert(&object),
+            Cached::Gone(status) => Err(gone(id, status)),
+        }
+    }
+
+    /// The object with the id `id`, fetched unless it's cached.
+    pub async fn object(&self, id: &str) -> Result<Document<Object>> {
+        match self.get(id, Kind::Object).await? {
+            Cached::Object(object) => Ok(*object),
+            Cached::Actor(actor) => convert(&actor),
+            Cached::Gone(status) => Err(gone(id, status)),
+        }
+    }
+
+    /// The cached object with the id `id`, if it's still fresh.
+    pub fn cached(&self, id: &str) -> Option<Cached> {
+        self.lock()
+            .get(id)
+            .filter(|entry| entry.expires > Instant::now())
+            .map(|entry| entry.value.clone())
+    }
+
+    /// How long ago the object with the id `id` was fetched or revalidated, if
+    /// it's cached, fresh or not.
+    pub fn age(&self, id: &str) -> Option<Duration> {
+        self.lock().get(id).map(|entry| entry.fetched.elapsed())
+    }
+
+    /// Forgets the object with the id `id`, e.g. because an `Update` or
+    /// `Delete` of it arrived, so that it's fetched again when next needed.
+    pub fn invalidate(&self, id: &str) {
+        self.lock().remove(id);
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    async fn get(&self, id: &str, kind: Kind) -> Result<Cached> {
+        let etag = match self.lock().get(id) {
+            Some(entry) if entry.expires > Instant::now() => return Ok(entry.value.clone()),
+            Some(entry) => entry.etag.clone(),
+            None => None,
+        };
+
+        let fetched = match self.fetcher.fetch_conditional(id, etag.as_deref()).await {
+            Ok(Fetched { document: None, .. }) if etag.is_none() => {
+                return Err(Error::Validation(format!(
+                    "{id} was fetched without a body"
+                )))
+            }
+            Ok(fetched) => fetched,
+            Err(Error::Http { status, .. })
+                if status == StatusCode::NOT_FOUND || status == StatusCode::GONE =>
+            {
+                self.insert(id, Cached::Gone(status), None, self.tombstone_ttl);
+                return Err(gone(id, status));
+            }
+            Err(err) => return Err(err),
+        };
+        let ttl = match fetched.cache_control {
+            cache_control if cache_control.no_cache => Duration::ZERO,
+            cache_control => cache_control
+                .max_age
+                .unwrap_or(self.default_ttl)
+                .min(self.max_ttl),
+        };
+
+        let value = match fetched.document {
+            Some(document) => parse(document, kind)?,
+            // Not modified, unless the entry was invalidated while revalidating.
+            None => match self.cached_value(id) {
+                Some(value) => value,
+                None => match self.fetcher.fetch_conditional(id, None).await?.document {
+                    Some(document) => parse(document, kind)?,
+                    None => {
+                        return Err(Error::Validation(format!(
+                            "{id} was fetched without a body"
+                        )))
+                    }
+                },
+            },
+        };
+        if fetched.cache_control.no_store {
+            self.invalidate(id);
+        } else {
+            self.insert(id, value.clone(), fetched.etag.or(etag), ttl);
+        }
+        Ok(value)
+    }
+
+    fn insert(&self, id: &str, value: Cached, etag: Option<String>, ttl: Duration) {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        if entries.len() >= self.max_entries && !entries.contains_key(id) {
+            entries.retain(|_, entry| entry.expires > now);
+            while entries.len() >= self.max_entries.max(1) {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires)
+                    .map(|(id, _)| id.clone())
+                    .expect("the cache is full");
+                entries.remove(&oldest);
+            }
+        }
+        let entry = Entry {
+            value,
+            etag,
+            fetched: now,
+            expires: now + ttl,
+        };
+        entries.insert(id.to_string(), entry);
+    }
+
+    fn cached_value(&self, id: &str) -> Option<Cached> {
+        self.lock().get(id).map(|entry| entry.value.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().expect("cache lock poisoned")
+    }
+}
+
+fn parse(document: Value, kind: Kind) -> Result<Cached> {
+    Ok(match kind {
+        Kind::Actor => Cached::Actor(serde_json::from_value(document)?),
+        Kind::Object => Cached::Object(serde_json::from_value(document)?),
+    })
+}
+
+/// A cached document parsed as another type.
+fn convert<T, U>(document: &Document<T>) -> Result<Document<U>>
+where
+    T: serde::Serialize,
+    U: serde::de::DeserializeOwned,
+{
+    Ok(serde_json::from_value(serde_json::to_value(document)?)?)
+}
+
+fn gone(id: &str, status: StatusCode) -> Error {
+    Error::Http {
+        uri: id.to_string(),
+        status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::MockFetcher;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    const ALICE: &str = "https://example.social/users/alice";
+    const NOTE: &str = "https://example.social/notes/1";
+
+    fn alice(name: &str) -> Value {
+        json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": ALICE,
+          "type": "Person",
+          "name": name,
+          "preferredUsername": "alice",
+          "inbox": "https://example.social/users/alice/inbox"
+        })
+    }
+
+    fn cache() -> Cache<MockFetcher> {
+        let fetcher = MockFetcher::new();
+        fetcher.insert(ALICE, &alice("Alice")).unwrap();
+        fetcher
+            .insert(
+                NOTE,
+                &json!({
+                  "@context": "https://www.w3.org/ns/activitystreams",
+                  "id": NOTE,
+                  "type": "Note",
+                  "content": "Hello"
+                }),
+            )
+            .unwrap();
+        Cache::new(fetcher)
+    }
+
+    #[tokio::test]
+    async fn caches_documents() {
+        let cache = cache();
+        let actor = cache.actor(ALICE).await.unwrap();
+        assert_eq!(actor.object.preferred_username.as_deref(), Some("alice"));
+        cache.actor(ALICE).await.unwrap();
+        cache.object(NOTE).await.unwrap();
+        cache.object(NOTE).await.unwrap();
+        assert_eq!(cache.fetcher.requests(), vec![ALICE, NOTE]);
+        assert_eq!(cache.len(), 2);
+
+        // An actor is an object too.
+        let object = cache.object(ALICE).await.unwrap();
+        assert_eq!(object.object.name.as_deref(), Some("Alice"));
+        assert_eq!(cache.fetcher.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn invalidation() {
+        let cache = cache();
+        cache.actor(ALICE).await.unwrap();
+        cache
+            .fetcher
+            .insert(ALICE, &alice("Alice Updated"))
+            .unwrap();
+        let actor = cache.actor(ALICE).await.unwrap();
+        assert_eq!(actor.object.base.name.as_deref(), Some("Alice"));
+
+        assert!(cache.age(ALICE).unwrap() < Duration::from_secs(60));
+        cache.invalidate(ALICE);
+        assert!(cache.cached(ALICE).is_none());
+        assert_eq!(cache.age(ALICE), None);
+        let actor = cache.actor(ALICE).await.unwrap();
+        assert_eq!(actor.object.base.name.as_deref(), Some("Alice Updated"));
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn expiry_and_revalidation() {
+        let mut cache = cache();
+        cache.default_ttl = Duration::ZERO;
+        cache
+            .fetcher
+            .insert_with_etag(ALICE, &alice("Alice"), "\"v1\"")
+            .unwrap();
+        cache.actor(ALICE).await.unwrap();
+        assert!(cache.cached(ALICE).is_none());
+
+        // Revalidated, not modified: the cached document is still used.
+        let actor = cache.actor(ALICE).await.unwrap();
+        assert_eq!(actor.object.base.name.as_deref(), Some("Alice"));
+
+        cache
+            .fetcher
+            .insert_with_etag(ALICE, &alice("Alice Updated"), "\"v2\"")
+            .unwrap();
+        let actor = cache.actor(ALICE).await.unwrap();
+        assert_eq!(actor.object.base.name.as_deref(), Some("Alice Updated"));
+        assert_eq!(cache.fetcher.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn tombstones() {
+        let cache = cache();
+        cache
+            .fetcher
+            .insert_status("https://example.social/users/bob", StatusCode::GONE);
+        for _ in 0..2 {
+            assert!(matches!(
+                cache.actor("https://example.social/users/bob").await,
+                Err(Error::Http {
+                    status: StatusCode::GONE,
+                    ..
+                })
+            ));
+            assert!(matches!(
+                cache.object("https://example.social/notes/2").await,
+                Err(Error::Http {
+                    status: StatusCode::NOT_FOUND,
+                    ..
+                })
+            ));
+        }
+        assert_eq!(cache.fetcher.requests().len(), 2);
+        assert!(matches!(
+            cache.cached("https://example.social/users/bob"),
+            Some(Cached::Gone(StatusCode::GONE))
+        ));
+
+        // Other errors aren't cached.
+        cache.fetcher.insert_status(NOTE, StatusCode::BAD_GATEWAY);
+        assert!(cache.object(NOTE).await.is_err());
+        assert!(cache.cached(NOTE).is_none());
+    }
+
+    #[tokio::test]
+    async fn eviction() {
+        let mut cache = cache();
+        cache.max_entries = 1;
+        cache.actor(ALICE).await.unwrap();
+        cache.object(NOTE).await.unwrap();
+        assert_eq!(cache.len(), 1);
+        assert!(cache.cached(ALICE).is_none());
+        assert!(cache.cached(NOTE).is_some());
+    }
+}
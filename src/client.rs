@@ -0,0 +1,161 @@
+//! The HTTP transport used to talk to other servers.
+//!
+//! The crate doesn't pick an HTTP library: anything that can send an
+//! [http::Request] and return an [http::Response] implements [HttpClient].
+//! With the `reqwest` feature, `reqwest::Client` does.
+
+use async_trait::async_trait;
+use http::{header, HeaderMap, Request, Response, Uri};
+
+use crate::{Error, Result};
+
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+    /// Sends a request and returns the response, whatever its status.
+    async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>>;
+
+    /// [HttpClient::execute], failing if the response body is larger than
+    /// `max_body_size` bytes. Clients should stop reading the body once it's
+    /// too large, as the reqwest one does; by default, the whole body is read
+    /// before it's checked.
+    async fn execute_limited(
+        &self,
+        request: Request<Vec<u8>>,
+        max_body_size: usize,
+    ) -> Result<Response<Vec<u8>>> {
+        let uri = request.uri().clone();
+        let response = self.execute(request).await?;
+        check_body_size(&uri, response.headers(), max_body_size)?;
+        if response.body().len() > max_body_size {
+            return Err(too_large(&uri, max_body_size));
+        }
+        Ok(response)
+    }
+}
+
+/// Checks the `Content-Length` of a response, before its body is read.
+pub(crate) fn check_body_size(uri: &Uri, headers: &HeaderMap, max_body_size: usize) -> Result<()> {
+    let declared = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<usize>().ok())
+        .unwrap_or_default();
+    if declared > max_body_size {
+        return Err(too_large(uri, max_body_size));
+    }
+    Ok(())
+}
+
+fn too_large(uri: &Uri, max_body_size: usize) -> Error {
+    Error::Validation(format!("{uri} is larger than {max_body_size} bytes"))
+}
+
+#[cfg(feature = "reqwest")]
+#[async_trait]
+impl HttpClient for reqwest::Client {
+    async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+        self.execute_limited(request, usize::MAX).await
+    }
+
+    async fn execute_limited(
+        &self,
+        request: Request<Vec<u8>>,
+        max_body_size: usize,
+    ) -> Result<Response<Vec<u8>>> {
+        let transport = |err: reqwest::Error| Error::Transport(err.to_string());
+        let uri = request.uri().clone();
+        let request = reqwest::Request::try_from(request).map_err(transport)?;
+        let mut response = reqwest::Client::execute(self, request)
+            .await
+            .map_err(transport)?;
+
+        let mut builder = Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        check_body_size(&uri, response.headers(), max_body_size)?;
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(transport)? {
+            if body.len() + chunk.len() > max_body_size {
+                return Err(too_large(&uri, max_body_size));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        builder
+            .body(body)
+            .map_err(|err| Error::Transport(err.to_string()))
+    }
+}
+
+/// A `reqwest::Client` that doesn't follow redirects, leaving them to the
+/// caller, e.g. [HttpFetcher](crate::fetch::HttpFetcher).
+#[cfg(feature = "reqwest")]
+pub fn reqwest_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|err| crate::Error::Transport(err.to_string()))
+}
+
+/// A copy of a request, which [Request] doesn't provide because bodies can't
+/// always be cloned.
+pub(crate) fn clone_request(request: &Request<Vec<u8>>) -> Request<Vec<u8>> {
+    let mut clone = Request::new(request.body().clone());
+    *clone.method_mut() = request.method().clone();
+    *clone.uri_mut() = request.uri().clone();
+    *clone.version_mut() = request.version();
+    *clone.headers_mut() = request.headers().clone();
+    clone
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serves one response with an endless chunked body.
+    #[cfg(feature = "reqwest")]
+    fn endless_server() -> String {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\ncontent-type: application/activity+json\r\ntransfer-encoding: chunked\r\n\r\n",
+            );
+            let chunk = format!("400\r\n{}\r\n", "a".repeat(0x400));
+            // Stops once the client hangs up.
+            while stream.write_all(chunk.as_bytes()).is_ok() {}
+        });
+        format!("http://{address}/users/alice")
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn stops_reading_large_bodies() {
+        let request = Request::get(endless_server()).body(Vec::new()).unwrap();
+        let result = reqwest_client()
+            .unwrap()
+            .execute_limited(request, 16 * 1024)
+            .await;
+        match result {
+            Err(Error::Validation(message)) => assert!(message.contains("larger than 16384")),
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn declared_length() {
+        let headers = HeaderMap::from_iter([(header::CONTENT_LENGTH, "2048".parse().unwrap())]);
+        let uri = Uri::from_static("https://example.social/users/alice");
+        assert!(check_body_size(&uri, &headers, 1024).is_err());
+        assert!(check_body_size(&uri, &headers, 2048).is_ok());
+    }
+}
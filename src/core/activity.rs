@@ -1,7 +1,11 @@
 use crate::core::actor::{Actor, ActorBuilder};
-use crate::core::object::{Object, ObjectBuilder};
+use crate::core::object::{Object, ObjectBuilder, ObjectOrLink};
+use crate::core::one_or_many::OneOrMany;
 use derive_builder::Builder;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
 
 ///////////////////////////////
 // Activity
@@ -21,72 +25,294 @@ pub struct Activity {
     pub base: Object,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub actor: Option<Actor>,
+    pub actor: Option<OneOrMany<ObjectOrLink<Actor>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub object: Option<Object>,
+    pub object: Option<OneOrMany<ObjectOrLink>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub target: Option<Object>,
+    pub target: Option<OneOrMany<ObjectOrLink>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub result: Option<String>,
+    pub result: Option<OneOrMany<ObjectOrLink>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub to: Option<Vec<String>>,
-
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub origin: Option<String>, // TODO: Origin
+    pub origin: Option<OneOrMany<ObjectOrLink>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub instrument: Option<String>, // TODO: Instrument
+    pub instrument: Option<OneOrMany<ObjectOrLink>>,
+}
+
+impl Activity {
+    /// The typed [ActivityType] of this activity, parsed from the `type`
+    /// property. Returns `None` if the activity has no `type`.
+    pub fn activity_type(&self) -> Option<ActivityType> {
+        self.base.object_type.as_deref().map(ActivityType::from)
+    }
 }
 
 impl ActivityBuilder {
+    pub fn of_activity_type(t: ActivityType) -> Self {
+        ActivityBuilder::default()
+            .base(Object {
+                object_type: Some(t.to_string()),
+                ..Default::default()
+            })
+            .to_owned()
+    }
+
     // TODO: macro
+    /// Builds the base [Object] with `build_fn`.
+    ///
+    /// # Panics
+    ///
+    /// If the base can't be built; see [ActivityBuilder::try_with_base].
     pub fn with_base<F>(&mut self, build_fn: F) -> &mut Self
+    where
+        F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
+    {
+        self.try_with_base(build_fn)
+            .expect("failed to build the activity base")
+    }
+
+    pub fn try_with_base<F>(&mut self, build_fn: F) -> crate::Result<&mut Self>
     where
         F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
     {
         let mut base_builder = ObjectBuilder::default();
-        self.base(build_fn(&mut base_builder).build().unwrap())
+        Ok(self.base(build_fn(&mut base_builder).build()?))
     }
 
+    /// Builds the `object` with `build_fn`.
+    ///
+    /// # Panics
+    ///
+    /// If the object can't be built; see [ActivityBuilder::try_with_object].
     pub fn with_object<F>(&mut self, build_fn: F) -> &mut Self
+    where
+        F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
+    {
+        self.try_with_object(build_fn)
+            .expect("failed to build the activity object")
+    }
+
+    pub fn try_with_object<F>(&mut self, build_fn: F) -> crate::Result<&mut Self>
     where
         F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
     {
         let mut base_builder = ObjectBuilder::default();
-        self.object(Some(build_fn(&mut base_builder).build().unwrap()))
+        Ok(self.object(Some(OneOrMany::One(ObjectOrLink::object(
+            build_fn(&mut base_builder).build()?,
+        )))))
     }
 
+    /// Builds the `actor` with `build_fn`.
+    ///
+    /// # Panics
+    ///
+    /// If the actor can't be built; see [ActivityBuilder::try_with_actor].
     pub fn with_actor<F>(&mut self, build_fn: F) -> &mut Self
+    where
+        F: FnOnce(&mut ActorBuilder) -> &mut ActorBuilder,
+    {
+        self.try_with_actor(build_fn)
+            .expect("failed to build the activity actor")
+    }
+
+    pub fn try_with_actor<F>(&mut self, build_fn: F) -> crate::Result<&mut Self>
     where
         F: FnOnce(&mut ActorBuilder) -> &mut ActorBuilder,
     {
         let mut base_builder = ActorBuilder::default();
-        self.actor(Some(build_fn(&mut base_builder).build().unwrap()))
+        Ok(self.actor(Some(OneOrMany::One(ObjectOrLink::object(
+            build_fn(&mut base_builder).build()?,
+        )))))
     }
 
+    /// Builds the `target` with `build_fn`.
+    ///
+    /// # Panics
+    ///
+    /// If the target can't be built; see [ActivityBuilder::try_with_target].
     pub fn with_target<F>(&mut self, build_fn: F) -> &mut Self
+    where
+        F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
+    {
+        self.try_with_target(build_fn)
+            .expect("failed to build the activity target")
+    }
+
+    pub fn try_with_target<F>(&mut self, build_fn: F) -> crate::Result<&mut Self>
     where
         F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
     {
         let mut base_builder = ObjectBuilder::default();
-        self.target(Some(build_fn(&mut base_builder).build().unwrap()))
+        Ok(self.target(Some(OneOrMany::One(ObjectOrLink::object(
+            build_fn(&mut base_builder).build()?,
+        )))))
     }
+
     /// Instances of [IntransitiveActivity] are a subtype of [Activity] representing
     /// intransitive actions. The object property is therefore inappropriate for
     /// these activities.
     pub fn intransitive_activity(object_type: String) -> Self {
-        let obj = ObjectBuilder::default()
-            .object_type(Some(object_type))
-            .build()
-            .unwrap();
+        let obj = Object {
+            object_type: Some(object_type),
+            ..Default::default()
+        };
         ActivityBuilder::default().base(obj).object(None).to_owned()
     }
 }
 
+///////////////////////////////
+// Activity Types
+// ////////////////////////////
+// https://www.w3.org/TR/activitystreams-vocabulary/#activity-types
+
+macro_rules! activity_types {
+    ($($(#[$doc:meta])* $variant:ident),+ $(,)?) => {
+        /// The extended [Activity] types defined by the Activity Vocabulary.
+        /// Any `type` not in the vocabulary is kept verbatim in
+        /// [ActivityType::Other] so that it survives a round trip.
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum ActivityType {
+            $($(#[$doc])* $variant,)+
+            /// A type outside the Activity Vocabulary, e.g. an extension type.
+            Other(String),
+        }
+
+        impl ActivityType {
+            /// All the activity types defined by the Activity Vocabulary.
+            pub const ALL: &'static [ActivityType] = &[$(ActivityType::$variant),+];
+
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(ActivityType::$variant => stringify!($variant),)+
+                    ActivityType::Other(t) => t.as_str(),
+                }
+            }
+        }
+
+        impl From<&str> for ActivityType {
+            fn from(t: &str) -> Self {
+                match t {
+                    $(stringify!($variant) => ActivityType::$variant,)+
+                    other => ActivityType::Other(other.to_string()),
+                }
+            }
+        }
+    };
+}
+
+activity_types! {
+    /// The actor accepts the object.
+    Accept,
+    /// The actor added the object to the target.
+    Add,
+    /// The actor is calling the target's attention to the object.
+    Announce,
+    /// An intransitive activity: the actor has arrived at the location.
+    Arrive,
+    /// The actor is blocking the object.
+    Block,
+    /// The actor has created the object.
+    Create,
+    /// The actor has deleted the object.
+    Delete,
+    /// The actor dislikes the object.
+    Dislike,
+    /// The actor is flagging the object as inappropriate.
+    Flag,
+    /// The actor is "following" the object.
+    Follow,
+    /// The actor is ignoring the object.
+    Ignore,
+    /// The actor is extending an invitation for the object to the target.
+    Invite,
+    /// The actor has joined the object.
+    Join,
+    /// The actor has left the object.
+    Leave,
+    /// The actor likes, recommends or endorses the object.
+    Like,
+    /// The actor has listened to the object.
+    Listen,
+    /// The actor has moved the object from origin to target.
+    Move,
+    /// The actor is offering the object.
+    Offer,
+    /// An intransitive activity representing a question being asked.
+    Question,
+    /// The actor is rejecting the object.
+    Reject,
+    /// The actor has read the object.
+    Read,
+    /// The actor is removing the object.
+    Remove,
+    /// A specialization of Reject in which the rejection is tentative.
+    TentativeReject,
+    /// A specialization of Accept indicating that the acceptance is tentative.
+    TentativeAccept,
+    /// An intransitive activity: the actor is traveling to the target from
+    /// the origin.
+    Travel,
+    /// The actor is undoing the object, a previous activity.
+    Undo,
+    /// The actor has updated the object.
+    Update,
+    /// The actor has viewed the object.
+    View,
+}
+
+impl ActivityType {
+    /// Whether this type is a subtype of IntransitiveActivity, i.e. it does
+    /// not carry an `object`.
+    pub fn is_intransitive(&self) -> bool {
+        matches!(
+            self,
+            ActivityType::Arrive | ActivityType::Question | ActivityType::Travel
+        )
+    }
+}
+
+impl From<String> for ActivityType {
+    fn from(t: String) -> Self {
+        ActivityType::from(t.as_str())
+    }
+}
+
+impl FromStr for ActivityType {
+    type Err = Infallible;
+
+    fn from_str(t: &str) -> Result<Self, Self::Err> {
+        Ok(ActivityType::from(t))
+    }
+}
+
+impl fmt::Display for ActivityType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for ActivityType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ActivityType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(ActivityType::from(String::deserialize(deserializer)?))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::{ContextBuilder, Document};
+    use crate::core::{ContextBuilder, Document, Link};
     use pretty_assertions::assert_eq;
     use serde_json::json;
 
@@ -158,13 +384,174 @@ mod tests {
         );
 
         assert!(activity.actor.is_some());
-        let actor = activity.actor.unwrap();
+        let actor = activity.actor.unwrap().into_first().unwrap();
+        let actor = actor.into_object().unwrap();
         assert_eq!(actor.base.object_type, Some("Person".into()));
         assert_eq!(actor.base.name, Some("Sally".into()));
 
         assert!(activity.object.is_some());
-        let object = activity.object.as_ref().unwrap();
+        let object = activity.object.as_ref().unwrap().first().unwrap();
+        let object = object.as_object().unwrap();
         assert_eq!(object.object_type, Some("Note".into()));
         assert_eq!(object.name, Some("A Note".into()));
     }
+
+    #[test]
+    fn try_with_helpers() -> crate::Result<()> {
+        let activity = ActivityBuilder::of_activity_type(ActivityType::Create)
+            .try_with_actor(|actor| actor.preferred_username(Some("sally".into())))?
+            .try_with_object(|object| object.name(Some("A note".into())))?
+            .build()?;
+        let actor = activity.actor.unwrap().into_first().unwrap();
+        assert_eq!(
+            actor.as_object().unwrap().preferred_username.as_deref(),
+            Some("sally")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_activity_type() {
+        let actual = json!({
+          "@context": {
+            "@vocab": "https://www.w3.org/ns/activitystreams"
+          },
+          "type": "Follow",
+          "actor": {
+            "type": "Person",
+            "name": "Sally"
+          },
+          "object": {
+            "type": "Person",
+            "name": "John"
+          }
+        })
+        .to_string();
+        let document: Document<Activity> = Document::deserialize_string(actual).unwrap();
+        match document.object.activity_type() {
+            Some(ActivityType::Follow) => {}
+            other => panic!("expected Follow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_activity_type_round_trips() {
+        let actual = json!({
+          "@context": {
+            "@vocab": "https://www.w3.org/ns/activitystreams"
+          },
+          "type": "EmojiReact",
+          "content": "🔥"
+        });
+        let document: Document<Activity> =
+            Document::deserialize_string(actual.to_string()).unwrap();
+        assert_eq!(
+            document.object.activity_type(),
+            Some(ActivityType::Other("EmojiReact".into()))
+        );
+        assert_eq!(serde_json::to_value(document).unwrap(), actual);
+    }
+
+    #[test]
+    fn activity_type_strings() {
+        assert_eq!(ActivityType::ALL.len(), 28);
+        for t in ActivityType::ALL {
+            assert_eq!(&t.to_string().parse::<ActivityType>().unwrap(), t);
+            assert_eq!(serde_json::to_value(t).unwrap(), json!(t.as_str()));
+        }
+        assert!(ActivityType::Travel.is_intransitive());
+        assert!(!ActivityType::Create.is_intransitive());
+    }
+
+    #[test]
+    fn serialize_activity_type() {
+        let activity = ActivityBuilder::of_activity_type(ActivityType::Like)
+            .with_object(|builder| builder.id(Some("http://example.org/note/1".into())))
+            .build()
+            .unwrap();
+        let actual = Document::new(ContextBuilder::default().build().unwrap(), activity);
+        let expected = json!({
+          "@context": {
+            "@vocab": "https://www.w3.org/ns/activitystreams"
+          },
+          "type": "Like",
+          "object": {
+            "id": "http://example.org/note/1"
+          }
+        });
+        assert_eq!(serde_json::to_value(actual).unwrap(), expected);
+    }
+
+    #[test]
+    fn deserialize_activity_with_iris() {
+        // As sent by Mastodon: actor and object are bare IRIs.
+        let actual = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": "https://mastodon.example/users/alice#follows/1234",
+          "type": "Follow",
+          "actor": "https://mastodon.example/users/alice",
+          "object": "https://example.com/users/bob"
+        })
+        .to_string();
+        let document: Document<Activity> = Document::deserialize_string(actual).unwrap();
+        let activity = document.object;
+        assert_eq!(
+            activity.actor.as_ref().and_then(|a| a.first()?.as_iri()),
+            Some("https://mastodon.example/users/alice")
+        );
+        assert_eq!(
+            activity.object.as_ref().and_then(|o| o.first()?.id()),
+            Some("https://example.com/users/bob")
+        );
+    }
+
+    #[test]
+    fn serialize_activity_with_link() {
+        let link = Link::new("http://example.org/abc".into(), "text/html".into());
+        let activity = ActivityBuilder::of_activity_type(ActivityType::Announce)
+            .actor(Some("http://sally.example.org".into()))
+            .object(Some(link.into()))
+            .build()
+            .unwrap();
+        let expected = json!({
+          "type": "Announce",
+          "actor": "http://sally.example.org",
+          "object": {
+            "type": "Link",
+            "href": "http://example.org/abc",
+            "mediaType": "text/html"
+          }
+        });
+        let value = serde_json::to_value(&activity).unwrap();
+        assert_eq!(value, expected);
+
+        let activity: Activity = serde_json::from_value(value).unwrap();
+        let object = activity.object.unwrap().into_first().unwrap();
+        assert_eq!(
+            object.as_link().map(|l| l.href.to_string()),
+            Some("http://example.org/abc".into())
+        );
+    }
+
+    #[test]
+    fn addressing_keeps_its_shape() {
+        let single = json!({
+          "type": "Create",
+          "to": "https://www.w3.org/ns/activitystreams#Public"
+        });
+        let activity: Activity = serde_json::from_value(single.clone()).unwrap();
+        assert_eq!(activity.base.to.as_ref().map(OneOrMany::len), Some(1));
+        assert_eq!(serde_json::to_value(&activity).unwrap(), single);
+
+        let many = json!({
+          "type": "Create",
+          "to": [
+            "https://www.w3.org/ns/activitystreams#Public",
+            "https://example.com/users/bob/followers"
+          ]
+        });
+        let activity: Activity = serde_json::from_value(many.clone()).unwrap();
+        assert_eq!(activity.base.to.as_ref().map(OneOrMany::len), Some(2));
+        assert_eq!(serde_json::to_value(&activity).unwrap(), many);
+    }
 }
@@ -1,8 +1,13 @@
-use crate::core::object::{Object, ObjectBuilder};
+use crate::core::extension::Extensions;
+use crate::core::object::{Object, ObjectBuilder, ObjectOrLink, UnexpectedType};
+use crate::core::one_or_many::OneOrMany;
 use derive_builder::Builder;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 
-// TODO: expand to actor types: https://www.w3.org/TR/activitystreams-vocabulary/#actor-types
+/// Actor types are [Object] types that are capable of performing activities.
+/// [Actor] is the common representation; the typed wrappers below ([Person],
+/// [Group], ...) guarantee the `type` property.
 #[derive(Serialize, Deserialize, Default, Debug, Clone, Builder)]
 #[builder(default)]
 pub struct Actor {
@@ -25,21 +30,166 @@ pub struct Actor {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub liked: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoints: Option<Endpoints>,
+
     #[serde(rename = "publicKey", skip_serializing_if = "Option::is_none")]
     pub public_key_info: Option<PublicKeyInfo>,
+
+    /// Keys the actor signs with, as in
+    /// [FEP-521a](https://codeberg.org/fediverse/fep/src/branch/main/fep/521a/fep-521a.md).
+    /// Entries may also be the IRI of a key published elsewhere, or a key in
+    /// another format.
+    #[serde(rename = "assertionMethod", skip_serializing_if = "Option::is_none")]
+    pub assertion_method: Option<OneOrMany<ObjectOrLink<VerificationMethod>>>,
+}
+
+impl AsRef<Object> for Actor {
+    fn as_ref(&self) -> &Object {
+        &self.base
+    }
+}
+
+impl From<Actor> for ObjectOrLink<Actor> {
+    fn from(actor: Actor) -> Self {
+        ObjectOrLink::object(actor)
+    }
 }
 
 impl ActorBuilder {
+    /// Builds the base [Object] with `build_fn`.
+    ///
+    /// # Panics
+    ///
+    /// If the base can't be built; see [ActorBuilder::try_with_base].
     pub fn with_base<F>(&mut self, build_fn: F) -> &mut Self
+    where
+        F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
+    {
+        self.try_with_base(build_fn)
+            .expect("failed to build the actor base")
+    }
+
+    pub fn try_with_base<F>(&mut self, build_fn: F) -> crate::Result<&mut Self>
     where
         F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
     {
         let mut base_builder = ObjectBuilder::default();
-        self.base(build_fn(&mut base_builder).build().unwrap())
+        Ok(self.base(build_fn(&mut base_builder).build()?))
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///////////////////////////
+// Actor Types
+///////////////////////////
+// https://www.w3.org/TR/activitystreams-vocabulary/#actor-types
+
+macro_rules! actor_types {
+    ($($(#[$doc:meta])* $name:ident => $builder:ident),+ $(,)?) => { $(
+        $(#[$doc])*
+        #[derive(Serialize, Deserialize, Debug, Clone, Builder)]
+        #[serde(try_from = "Actor", into = "Actor")]
+        #[builder(default)]
+        pub struct $name {
+            pub base: Actor,
+        }
+
+        impl $name {
+            /// The value of the `type` property for this actor type.
+            pub const TYPE: &'static str = stringify!($name);
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                let mut base = Actor::default();
+                base.base.object_type = Some(Self::TYPE.into());
+                $name { base }
+            }
+        }
+
+        impl $builder {
+            /// Builds the base [Actor] with `build_fn`.
+            ///
+            /// # Panics
+            ///
+            /// If the actor can't be built; see `try_with_actor`.
+            pub fn with_actor<F>(&mut self, build_fn: F) -> &mut Self
+            where
+                F: FnOnce(&mut ActorBuilder) -> &mut ActorBuilder,
+            {
+                self.try_with_actor(build_fn)
+                    .expect("failed to build the actor")
+            }
+
+            pub fn try_with_actor<F>(&mut self, build_fn: F) -> crate::Result<&mut Self>
+            where
+                F: FnOnce(&mut ActorBuilder) -> &mut ActorBuilder,
+            {
+                let mut base_builder = ActorBuilder::default();
+                let mut actor = build_fn(&mut base_builder).build()?;
+                actor.base.object_type = Some($name::TYPE.into());
+                Ok(self.base(actor))
+            }
+        }
+
+        impl TryFrom<Actor> for $name {
+            type Error = UnexpectedType;
+
+            fn try_from(actor: Actor) -> Result<Self, Self::Error> {
+                match actor.base.object_type.as_deref() {
+                    Some($name::TYPE) => Ok($name { base: actor }),
+                    found => Err(UnexpectedType {
+                        expected: $name::TYPE,
+                        found: found.map(String::from),
+                    }),
+                }
+            }
+        }
+
+        impl From<$name> for Actor {
+            fn from(typed: $name) -> Self {
+                let mut actor = typed.base;
+                actor.base.object_type = Some($name::TYPE.into());
+                actor
+            }
+        }
+
+        impl AsRef<Actor> for $name {
+            fn as_ref(&self) -> &Actor {
+                &self.base
+            }
+        }
+    )+ };
+}
+
+actor_types! {
+    /// Describes a software application.
+    Application => ApplicationBuilder,
+    /// Represents a formal or informal collective of Actors.
+    Group => GroupBuilder,
+    /// Represents an organization.
+    Organization => OrganizationBuilder,
+    /// Represents an individual person.
+    Person => PersonBuilder,
+    /// Represents a service of any kind.
+    Service => ServiceBuilder,
+}
+
+/// Endpoints of the actor's server that may be useful to the actor or to
+/// those that reference it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoints {
+    /// An inbox that delivers to all the actors of the server an activity is
+    /// addressed to, so that it needn't be sent to each of them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared_inbox: Option<String>,
+
+    #[serde(flatten)]
+    pub extensions: Extensions,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PublicKeyInfo {
     pub id: String,
@@ -47,11 +197,69 @@ pub struct PublicKeyInfo {
     pub public_key_pem: String,
 }
 
+/// A public key in the
+/// [Multikey](https://www.w3.org/TR/controller-document/#multikey) format: the
+/// key is encoded with a multicodec prefix and multibase, e.g.
+/// `z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2` for an Ed25519 key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Multikey {
+    pub id: String,
+
+    #[serde(rename = "type")]
+    pub key_type: String,
+
+    pub controller: String,
+
+    pub public_key_multibase: String,
+
+    #[serde(flatten)]
+    pub extensions: Extensions,
+}
+
+impl Multikey {
+    /// The value of the `type` property.
+    pub const TYPE: &'static str = "Multikey";
+
+    pub fn new(id: String, controller: String, public_key_multibase: String) -> Self {
+        Multikey {
+            id,
+            key_type: Multikey::TYPE.into(),
+            controller,
+            public_key_multibase,
+            extensions: Extensions::new(),
+        }
+    }
+}
+
+/// An embedded entry of `assertionMethod`: a [Multikey], or a key in a
+/// format this crate doesn't read, e.g. `JsonWebKey2020`, kept as is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum VerificationMethod {
+    Multikey(Multikey),
+    Other(serde_json::Value),
+}
+
+impl VerificationMethod {
+    pub fn as_multikey(&self) -> Option<&Multikey> {
+        match self {
+            VerificationMethod::Multikey(multikey) => Some(multikey),
+            VerificationMethod::Other(_) => None,
+        }
+    }
+}
+
+impl From<Multikey> for VerificationMethod {
+    fn from(multikey: Multikey) -> Self {
+        VerificationMethod::Multikey(multikey)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::core::{ContextBuilder, Document};
-    use http::Uri;
     use pretty_assertions::assert_eq;
     use serde_json::json;
 
@@ -60,9 +268,7 @@ mod tests {
         let person = ActorBuilder::default()
             .with_base(|base| {
                 base.object_type(Some("Person".into()))
-                    .id(Some(
-                        "https://example.com/person/1234".parse::<Uri>().unwrap(),
-                    ))
+                    .id(Some("https://example.com/person/1234".to_string()))
                     .name(Some("name".into()))
             })
             .preferred_username(Some("dma".into()))
@@ -101,9 +307,207 @@ mod tests {
         assert_eq!(actor.base.object_type, Some("Person".into()));
         assert_eq!(
             actor.base.id,
-            Some("https://example.com/person/1234".parse::<Uri>().unwrap())
+            Some("https://example.com/person/1234".into())
         );
         assert_eq!(actor.base.name, Some("name".into()));
         assert_eq!(actor.preferred_username, Some("dma".into()));
     }
+
+    #[test]
+    fn serialize_person() {
+        let person = PersonBuilder::default()
+            .with_actor(|actor| {
+                actor
+                    .with_base(|base| {
+                        base.id(Some("https://example.com/person/1234".to_string()))
+                            .name(Some("name".into()))
+                    })
+                    .inbox(Some("https://example.com/person/1234/inbox".into()))
+                    .outbox(Some("https://example.com/person/1234/outbox".into()))
+                    .public_key_info(Some(PublicKeyInfo {
+                        id: "https://example.com/person/1234#main-key".into(),
+                        owner: "https://example.com/person/1234".into(),
+                        public_key_pem: "-----BEGIN PUBLIC KEY-----...".into(),
+                    }))
+            })
+            .build()
+            .unwrap();
+        let context = ContextBuilder::new().build().unwrap();
+        let actual = Document::new(context, person);
+        let expected = json!({
+          "@context": {
+            "@vocab": "https://www.w3.org/ns/activitystreams"
+          },
+          "type": "Person",
+          "id": "https://example.com/person/1234",
+          "name": "name",
+          "inbox": "https://example.com/person/1234/inbox",
+          "outbox": "https://example.com/person/1234/outbox",
+          "publicKey": {
+            "id": "https://example.com/person/1234#main-key",
+            "owner": "https://example.com/person/1234",
+            "publicKeyPem": "-----BEGIN PUBLIC KEY-----..."
+          }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected)
+    }
+
+    #[test]
+    fn serialize_default_actor_types() {
+        let context = ContextBuilder::new().build().unwrap();
+        let expected = |t: &str| {
+            json!({
+              "@context": {
+                "@vocab": "https://www.w3.org/ns/activitystreams"
+              },
+              "type": t
+            })
+        };
+        let value = |actor: Actor| serde_json::to_value(Document::new(context.clone(), actor));
+        assert_eq!(
+            value(Application::default().into()).unwrap(),
+            expected("Application")
+        );
+        assert_eq!(value(Group::default().into()).unwrap(), expected("Group"));
+        assert_eq!(
+            value(Organization::default().into()).unwrap(),
+            expected("Organization")
+        );
+        assert_eq!(value(Person::default().into()).unwrap(), expected("Person"));
+        assert_eq!(
+            value(Service::default().into()).unwrap(),
+            expected("Service")
+        );
+    }
+
+    #[test]
+    fn deserialize_group() {
+        let actual = json!({
+          "@context": {
+            "@vocab": "https://www.w3.org/ns/activitystreams"
+          },
+          "type": "Group",
+          "name": "Big Beards of Austin",
+          "inbox": "https://example.com/group/beards/inbox"
+        })
+        .to_string();
+        let document: Document<Group> = Document::deserialize_string(actual).unwrap();
+        let group = document.object;
+        assert_eq!(group.base.base.name, Some("Big Beards of Austin".into()));
+        assert_eq!(
+            group.base.inbox,
+            Some("https://example.com/group/beards/inbox".into())
+        );
+    }
+
+    #[test]
+    fn deserialize_wrong_actor_type() {
+        let actual = json!({
+          "@context": {
+            "@vocab": "https://www.w3.org/ns/activitystreams"
+          },
+          "type": "Service",
+          "name": "Acme Web Service"
+        })
+        .to_string();
+        let result: crate::Result<Document<Person>> = Document::deserialize_string(actual);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn assertion_method() {
+        let actual = json!({
+          "@context": [
+            "https://www.w3.org/ns/activitystreams",
+            "https://w3id.org/security/data-integrity/v1"
+          ],
+          "id": "https://server.example/users/alice",
+          "type": "Person",
+          "inbox": "https://server.example/users/alice/inbox",
+          "assertionMethod": [
+            {
+              "id": "https://server.example/users/alice#ed25519-key",
+              "type": "Multikey",
+              "controller": "https://server.example/users/alice",
+              "publicKeyMultibase": "z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2"
+            }
+          ]
+        });
+        let document: Document<Person> = Document::deserialize_string(actual.to_string()).unwrap();
+        let methods = document.object.base.assertion_method.as_ref().unwrap();
+        let multikey = methods
+            .first()
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .as_multikey()
+            .unwrap();
+        assert_eq!(multikey.key_type, Multikey::TYPE);
+        assert_eq!(multikey.controller, "https://server.example/users/alice");
+        assert_eq!(serde_json::to_value(&document).unwrap(), actual);
+    }
+
+    #[test]
+    fn unknown_assertion_method() {
+        let actual = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": "https://server.example/users/alice",
+          "type": "Person",
+          "inbox": "https://server.example/users/alice/inbox",
+          "assertionMethod": [
+            {
+              "id": "https://server.example/users/alice#jwk",
+              "type": "JsonWebKey2020",
+              "controller": "https://server.example/users/alice",
+              "publicKeyJwk": { "kty": "OKP", "crv": "Ed25519", "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo" }
+            }
+          ]
+        });
+        let document: Document<Person> = Document::deserialize_string(actual.to_string()).unwrap();
+        let methods = document.object.base.assertion_method.as_ref().unwrap();
+        let method = methods.first().unwrap().as_object().unwrap();
+        assert!(matches!(method, VerificationMethod::Other(_)));
+        assert_eq!(serde_json::to_value(&document).unwrap(), actual);
+    }
+
+    #[test]
+    fn endpoints() {
+        let actual = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": "https://server.example/users/alice",
+          "type": "Person",
+          "inbox": "https://server.example/users/alice/inbox",
+          "endpoints": {
+            "sharedInbox": "https://server.example/inbox",
+            "proxyUrl": "https://server.example/proxy"
+          }
+        });
+        let document: Document<Person> = Document::deserialize_string(actual.to_string()).unwrap();
+        let endpoints = document.object.base.endpoints.as_ref().unwrap();
+        assert_eq!(
+            endpoints.shared_inbox.as_deref(),
+            Some("https://server.example/inbox")
+        );
+        assert_eq!(serde_json::to_value(&document).unwrap(), actual);
+    }
+
+    #[test]
+    fn try_from_actor() {
+        let actor = ActorBuilder::default()
+            .with_base(|base| base.object_type(Some("Organization".into())))
+            .build()
+            .unwrap();
+        assert!(Organization::try_from(actor.clone()).is_ok());
+        assert_eq!(
+            Person::try_from(actor).unwrap_err(),
+            UnexpectedType {
+                expected: "Person",
+                found: Some("Organization".into())
+            }
+        );
+        assert_eq!(
+            Application::try_from(Actor::default()).unwrap_err().found,
+            None
+        );
+    }
 }
@@ -1,36 +1,69 @@
-use serde::{ Deserialize, Serialize };
+use super::object::{Object, ObjectBuilder, ObjectOrLink};
 use derive_builder::Builder;
-use super::object::{ Object, ObjectBuilder };
+use serde::{Deserialize, Serialize};
 
 /// A [Collection] is a subtype of [Object] that represents ordered or unordered
 /// sets of [Object] or [Link] instances. Refer to the Activity Streams 2.0 Core
 /// specification for a complete description of the [Collection] type.
-#[derive(Serialize, Deserialize, Debug, Clone, Builder)]
-pub struct Collection<Item: Clone> { // TODO: can we avoid need for Clone?
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Builder)]
+pub struct Collection<Item: Clone> {
+    // TODO: can we avoid need for Clone?
     #[serde(flatten)]
     pub base: Object,
 
     #[serde(rename = "totalItems", skip_serializing_if = "Option::is_none")]
     pub total_items: Option<usize>,
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     pub items: Vec<Item>,
+    /// The first page of the collection, usually its IRI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub first: Option<ObjectOrLink>,
+
+    /// The last page of the collection, usually its IRI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub last: Option<ObjectOrLink>,
+
+    /// The page with the most recently updated items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub current: Option<ObjectOrLink>,
 }
 
 impl<Item: Clone> CollectionBuilder<Item> {
-
+    /// Builds the base [Object] with `build_fn`.
+    ///
+    /// # Panics
+    ///
+    /// If the base can't be built; see [CollectionBuilder::try_with_base].
     pub fn with_base<F>(&mut self, build_fn: F) -> &mut Self
-        where F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder
+    where
+        F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
+    {
+        self.try_with_base(build_fn)
+            .expect("failed to build the collection base")
+    }
+
+    pub fn try_with_base<F>(&mut self, build_fn: F) -> crate::Result<&mut Self>
+    where
+        F: FnOnce(&mut ObjectBuilder) -> &mut ObjectBuilder,
     {
         let mut base_builder = ObjectBuilder::default();
-        self.base(build_fn(&mut base_builder).build().unwrap())
+        Ok(self.base(build_fn(&mut base_builder).build()?))
     }
+}
 
+impl<Item: Clone> AsRef<Object> for Collection<Item> {
+    fn as_ref(&self) -> &Object {
+        &self.base
+    }
 }
 
 /// A subtype of [Collection] in which members of the logical collection are
 /// assumed to always be strictly ordered.
-#[derive(Serialize, Deserialize, Debug, Clone, Builder)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Builder)]
 pub struct OrderedCollection<CollectionT> {
     #[serde(flatten)]
     pub base: Object,
@@ -38,9 +71,23 @@ pub struct OrderedCollection<CollectionT> {
     #[serde(rename = "totalItems", skip_serializing_if = "Option::is_none")]
     pub total_items: Option<usize>,
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     #[serde(rename = "orderedItems")]
     pub ordered_items: Vec<CollectionT>,
+    /// The first page of the collection, usually its IRI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub first: Option<ObjectOrLink>,
+
+    /// The last page of the collection, usually its IRI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub last: Option<ObjectOrLink>,
+
+    /// The page with the most recently updated items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub current: Option<ObjectOrLink>,
 }
 
 /// Used to represent distinct subsets of items from a [Collection]. Refer to
@@ -54,8 +101,10 @@ pub struct CollectionPage<BaseCollection: Clone> {
     #[serde(rename = "partOf")]
     pub part_of: String,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub next: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub prev: Option<String>,
 }
 
@@ -70,7 +119,9 @@ pub struct OrderedCollectionPage<BaseCollection: Clone> {
     #[serde(rename = "partOf")]
     pub part_of: String,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub next: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub prev: Option<String>,
 }
@@ -0,0 +1,195 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::Result;
+
+use crate::core::activity::Activity;
+use crate::core::actor::{Actor, Application, Group, Multikey, Organization, Person, Service};
+use crate::core::collection::{
+    Collection, CollectionPage, OrderedCollection, OrderedCollectionPage,
+};
+use crate::core::object::{
+    Article, Audio, Event, Image, Link, Note, Object, Page, Place, Preview, Profile, Relationship,
+    Tombstone, Video,
+};
+use crate::core::proof::{DataIntegrityProof, LinkedDataSignature};
+
+/// Map of the properties a type doesn't model itself, keyed by their name in
+/// the JSON document.
+pub type Extensions = Map<String, Value>;
+
+///////////////////////////
+// Extensible
+///////////////////////////
+/// Access to the properties that were deserialized but are not part of the
+/// Activity Vocabulary as modelled by this crate, e.g. Mastodon's `sensitive`
+/// or `featured`. They are kept as JSON and serialized back unchanged, so
+/// that a received document can be stored or forwarded without losing data.
+///
+/// Types that extend [Object] share its extensions: an unknown property of an
+/// [Activity] is found in `activity.base.extensions`. An explicit `null` on a
+/// known property is read as the property being absent, as in JSON-LD.
+pub trait Extensible {
+    fn extensions(&self) -> &Extensions;
+
+    fn extensions_mut(&mut self) -> &mut Extensions;
+
+    fn extension(&self, name: &str) -> Option<&Value> {
+        self.extensions().get(name)
+    }
+
+    /// Deserializes an extension property into `T`. Returns `None` if the
+    /// property is absent.
+    fn extension_as<T: DeserializeOwned>(&self, name: &str) -> Option<Result<T>> {
+        self.extension(name)
+            .map(|value| Ok(serde_json::from_value(value.clone())?))
+    }
+
+    /// Sets an extension property. The name must not be one of the properties
+    /// modelled by the type, otherwise it is serialized twice.
+    fn set_extension<T: Serialize>(&mut self, name: &str, value: T) -> Result<()> {
+        let value = serde_json::to_value(value)?;
+        self.extensions_mut().insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn remove_extension(&mut self, name: &str) -> Option<Value> {
+        self.extensions_mut().remove(name)
+    }
+}
+
+macro_rules! extensible {
+    ($($name:ty),+ $(,)?) => { $(
+        impl Extensible for $name {
+            fn extensions(&self) -> &Extensions {
+                &self.extensions
+            }
+
+            fn extensions_mut(&mut self) -> &mut Extensions {
+                &mut self.extensions
+            }
+        }
+    )+ };
+}
+
+extensible!(
+    Object,
+    Link,
+    Preview,
+    Multikey,
+    DataIntegrityProof,
+    LinkedDataSignature
+);
+
+// Types that extend another type keep their extensions in their base.
+macro_rules! extensible_via_base {
+    ($(impl$(<$param:ident $(: $bound:ident)?>)? for $name:ty),+ $(,)?) => { $(
+        impl$(<$param $(: $bound)?>)? Extensible for $name {
+            fn extensions(&self) -> &Extensions {
+                self.base.extensions()
+            }
+
+            fn extensions_mut(&mut self) -> &mut Extensions {
+                self.base.extensions_mut()
+            }
+        }
+    )+ };
+}
+
+extensible_via_base! {
+    impl for Activity,
+    impl for Actor,
+    impl for Application,
+    impl for Group,
+    impl for Organization,
+    impl for Person,
+    impl for Service,
+    impl for Article,
+    impl for Audio,
+    impl for Event,
+    impl for Image,
+    impl for Note,
+    impl for Page,
+    impl for Video,
+    impl for Place,
+    impl for Profile,
+    impl for Relationship,
+    impl for Tombstone,
+    impl<T: Clone> for Collection<T>,
+    impl<T> for OrderedCollection<T>,
+    impl<T: Clone> for CollectionPage<T>,
+    impl<T: Clone> for OrderedCollectionPage<T>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Document;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn unknown_properties_round_trip() {
+        let actual = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "type": "Note",
+          "content": "Hello",
+          "sensitive": false,
+          "conversation": "tag:mastodon.example,2022-11-10:objectId=1:objectType=Conversation",
+          "attachment": {
+            "type": "Document",
+            "url": "https://files.mastodon.example/a.png",
+            "blurhash": "UBL_:rOpGG-oBUNG,qRj2so|=eE1w^n4S5NH",
+            "focalPoint": [0.0, 0.5]
+          }
+        });
+        let document: Document<Note> = Document::deserialize_string(actual.to_string()).unwrap();
+        let note = &document.object;
+        assert_eq!(note.extension("sensitive"), Some(&json!(false)));
+        let attachment = note.base.attachment.as_ref().unwrap().first().unwrap();
+        let attachment = attachment.as_object().unwrap();
+        assert_eq!(
+            attachment
+                .extension_as::<Vec<f64>>("focalPoint")
+                .unwrap()
+                .unwrap(),
+            vec![0.0, 0.5]
+        );
+        assert_eq!(serde_json::to_value(&document).unwrap(), actual);
+    }
+
+    #[test]
+    fn activity_extensions_live_in_the_base() {
+        let mut activity: Activity = serde_json::from_value(json!({
+          "type": "Follow",
+          "actor": "https://mastodon.example/users/alice",
+          "object": "https://example.com/users/bob",
+          "misskey:reaction": "👍"
+        }))
+        .unwrap();
+        assert_eq!(activity.extension("misskey:reaction"), Some(&json!("👍")));
+        assert!(activity.extension("actor").is_none());
+        assert_eq!(activity.base.extensions.len(), 1);
+
+        activity.remove_extension("misskey:reaction");
+        activity
+            .set_extension("quoteUrl", "https://example.com/notes/1")
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&activity).unwrap(),
+            json!({
+              "type": "Follow",
+              "actor": "https://mastodon.example/users/alice",
+              "object": "https://example.com/users/bob",
+              "quoteUrl": "https://example.com/notes/1"
+            })
+        );
+    }
+
+    #[test]
+    fn missing_extension() {
+        let object = Object::default();
+        assert!(object.extension("sensitive").is_none());
+        assert!(object.extension_as::<bool>("sensitive").is_none());
+    }
+}
@@ -1,12 +1,20 @@
 pub mod activity;
 pub mod actor;
 pub mod collection;
+pub mod extension;
 pub mod object;
+pub mod one_or_many;
+pub mod proof;
 
+pub use extension::{Extensible, Extensions};
 pub use object::*;
+pub use one_or_many::OneOrMany;
+pub use proof::{DataIntegrityProof, LinkedDataSignature};
 
 use derive_builder::Builder;
-use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 
 // TODO: rename to something else as there's a [Document] in the Activity
 // Streams spec.
@@ -14,7 +22,7 @@ use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 /// 2.0 object.
 #[derive(Serialize, Deserialize, Debug, Clone, Builder)]
 pub struct Document<T> {
-    #[serde(rename = "@context", deserialize_with = "context_deserializer")]
+    #[serde(rename = "@context")]
     pub context: Context,
 
     #[serde(flatten)]
@@ -26,16 +34,20 @@ impl<T: DeserializeOwned + Serialize> Document<T> {
         Document { context, object }
     }
 
-    pub fn serialize_pretty(&self) -> serde_json::Result<String> {
-        let serialized = serde_json::to_string_pretty(&self);
-        println!("serialized = {:?}", serialized);
-        serialized
+    pub fn serialize_pretty(&self) -> crate::Result<String> {
+        Ok(serde_json::to_string_pretty(&self)?)
     }
 }
 
 impl<T: DeserializeOwned + Serialize> Document<T> {
-    pub fn deserialize_string(json: String) -> serde_json::Result<Document<T>> {
-        serde_json::from_str(json.as_str())
+    pub fn deserialize_string(json: String) -> crate::Result<Document<T>> {
+        Ok(serde_json::from_str(json.as_str())?)
+    }
+
+    /// Deserializes a document from raw bytes, e.g. the body of an incoming
+    /// request.
+    pub fn deserialize_slice(json: &[u8]) -> crate::Result<Document<T>> {
+        Ok(serde_json::from_slice(json)?)
     }
 }
 
@@ -51,57 +63,212 @@ impl<T: DeserializeOwned + Serialize> Document<T> {
 /// alternative URL "http://www.w3.org/ns/activitystreams" instead. This can be
 /// done using a string, object, or array.
 /// <https://www.w3.org/TR/activitystreams-core/#jsonld>
+///
+/// A [Context] keeps whichever of those shapes it was deserialized from, so
+/// e.g. Mastodon's `["https://www.w3.org/ns/activitystreams",
+/// "https://w3id.org/security/v1", {"toot": "http://joinmastodon.org/ns#"}]`
+/// serializes back unchanged.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Context(OneOrMany<ContextEntry>);
 
-const NAMESPACE: &str = "https://www.w3.org/ns/activitystreams";
+pub const NAMESPACE: &str = "https://www.w3.org/ns/activitystreams";
 
-#[derive(Serialize, Deserialize, Debug, Clone, Builder)]
-#[builder(default)]
-pub struct Context {
-    #[serde(rename = "@vocab")]
-    namespace: String,
+/// The alternative, non-TLS URL of the Activity Streams 2.0 context.
+const NAMESPACE_HTTP: &str = "http://www.w3.org/ns/activitystreams";
 
-    #[serde(skip_serializing_if = "Option::is_none", rename = "@language")]
-    language: Option<String>,
+/// The special collection of everyone: objects addressed to it are public.
+/// It's also written `as:Public` or `Public`, see [is_public].
+pub const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+/// Whether an address is the [PUBLIC] collection.
+pub fn is_public(address: &str) -> bool {
+    matches!(address, PUBLIC | "as:Public" | "Public")
+}
+
+/// One entry of a [Context]: either a reference to a remote context document
+/// or an inline context definition.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ContextEntry {
+    Iri(String),
+    Definition(ContextDefinition),
+}
+
+/// An inline JSON-LD context definition: the `@vocab` and `@language`
+/// keywords plus any term definitions, e.g. `"toot": "http://joinmastodon.org/ns#"`
+/// or `"featured": {"@id": "toot:featured", "@type": "@id"}`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ContextDefinition {
+    #[serde(rename = "@vocab", skip_serializing_if = "Option::is_none")]
+    pub vocab: Option<String>,
+
+    #[serde(rename = "@language", skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    #[serde(flatten)]
+    pub terms: BTreeMap<String, serde_json::Value>,
 }
 
 impl Context {
     pub fn new() -> Self {
-        Context {
-            namespace: NAMESPACE.to_string(),
-            language: None,
+        Context::default()
+    }
+
+    pub fn entries(&self) -> &[ContextEntry] {
+        self.0.as_slice()
+    }
+
+    fn definitions(&self) -> impl Iterator<Item = &ContextDefinition> {
+        self.entries().iter().filter_map(|entry| match entry {
+            ContextEntry::Definition(definition) => Some(definition),
+            ContextEntry::Iri(_) => None,
+        })
+    }
+
+    /// The remote contexts referenced by IRI, in order.
+    pub fn iris(&self) -> impl Iterator<Item = &str> {
+        self.entries().iter().filter_map(|entry| match entry {
+            ContextEntry::Iri(iri) => Some(iri.as_str()),
+            ContextEntry::Definition(_) => None,
+        })
+    }
+
+    /// The default vocabulary (`@vocab`) of the first inline definition that
+    /// declares one.
+    pub fn namespace(&self) -> Option<&str> {
+        self.definitions().find_map(|d| d.vocab.as_deref())
+    }
+
+    /// The default language (`@language`) of the first inline definition that
+    /// declares one.
+    pub fn language(&self) -> Option<&str> {
+        self.definitions().find_map(|d| d.language.as_deref())
+    }
+
+    /// The inline definition of a term, e.g. `"toot"` or `"featured"`.
+    pub fn term(&self, name: &str) -> Option<&serde_json::Value> {
+        self.definitions().find_map(|d| d.terms.get(name))
+    }
+
+    /// Whether the Activity Streams 2.0 context is referenced, either by IRI
+    /// or as the `@vocab`.
+    pub fn is_activitystreams(&self) -> bool {
+        self.iris()
+            .chain(self.definitions().filter_map(|d| d.vocab.as_deref()))
+            .any(is_activitystreams)
+    }
+
+    /// The extension namespaces in use besides Activity Streams: remote
+    /// contexts such as `https://w3id.org/security/v1` and the namespaces
+    /// that inline terms are prefixes for, such as
+    /// `http://joinmastodon.org/ns#`.
+    pub fn extensions(&self) -> Vec<&str> {
+        let prefixes = self.definitions().flat_map(|d| {
+            d.terms
+                .values()
+                .filter_map(|value| value.as_str())
+                .filter(|iri| iri.contains("://") && (iri.ends_with('#') || iri.ends_with('/')))
+        });
+        let mut extensions = Vec::new();
+        for iri in self.iris().chain(prefixes) {
+            if !is_activitystreams(iri) && !extensions.contains(&iri) {
+                extensions.push(iri);
+            }
         }
+        extensions
     }
+
+    /// Whether an extension namespace is present, see [Context::extensions].
+    pub fn has_extension(&self, iri: &str) -> bool {
+        self.extensions().contains(&iri)
+    }
+}
+
+fn is_activitystreams(iri: &str) -> bool {
+    let iri = iri.trim_end_matches('#');
+    iri == NAMESPACE || iri == NAMESPACE_HTTP
 }
+
 impl Default for Context {
     fn default() -> Self {
-        Context {
-            namespace: NAMESPACE.to_string(),
-            language: None,
-        }
+        Context(OneOrMany::One(ContextEntry::Definition(
+            ContextDefinition {
+                vocab: Some(NAMESPACE.to_string()),
+                ..ContextDefinition::default()
+            },
+        )))
     }
 }
 
-fn context_deserializer<'de, D>(deserializer: D) -> Result<Context, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    #[derive(Deserialize, Debug)]
-    #[serde(untagged)]
-    enum ContextType<'a> {
-        Context(Context),
-        Str(&'a str),
-    }
-
-    Ok(match ContextType::deserialize(deserializer)? {
-        ContextType::Str(_x) => Context::new(),
-        ContextType::Context(x) => x,
-    })
+impl From<ContextEntry> for Context {
+    fn from(entry: ContextEntry) -> Self {
+        Context(OneOrMany::One(entry))
+    }
+}
+
+impl From<Vec<ContextEntry>> for Context {
+    fn from(entries: Vec<ContextEntry>) -> Self {
+        Context(OneOrMany::Many(entries))
+    }
+}
+
+/// Builds a [Context] with an inline definition for the Activity Streams
+/// vocabulary, followed by any extension contexts.
+#[derive(Debug, Clone, Default)]
+pub struct ContextBuilder {
+    namespace: Option<String>,
+    language: Option<String>,
+    terms: BTreeMap<String, serde_json::Value>,
+    extensions: Vec<String>,
 }
 
 impl ContextBuilder {
     pub fn new() -> Self {
         ContextBuilder::default()
     }
+
+    /// The `@vocab`, defaults to the Activity Streams namespace.
+    pub fn namespace(&mut self, namespace: String) -> &mut Self {
+        self.namespace = Some(namespace);
+        self
+    }
+
+    pub fn language(&mut self, language: Option<String>) -> &mut Self {
+        self.language = language;
+        self
+    }
+
+    /// Adds an inline term definition.
+    pub fn term<V: Into<serde_json::Value>>(&mut self, name: &str, value: V) -> &mut Self {
+        self.terms.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// References an extension context by IRI, e.g.
+    /// `https://w3id.org/security/v1`.
+    pub fn extension(&mut self, iri: &str) -> &mut Self {
+        self.extensions.push(iri.to_string());
+        self
+    }
+
+    pub fn build(&self) -> Result<Context, Infallible> {
+        let definition = ContextEntry::Definition(ContextDefinition {
+            vocab: Some(
+                self.namespace
+                    .clone()
+                    .unwrap_or_else(|| NAMESPACE.to_string()),
+            ),
+            language: self.language.clone(),
+            terms: self.terms.clone(),
+        });
+        if self.extensions.is_empty() {
+            return Ok(definition.into());
+        }
+        let mut entries = vec![definition];
+        entries.extend(self.extensions.iter().cloned().map(ContextEntry::Iri));
+        Ok(entries.into())
+    }
 }
 
 #[cfg(test)]
@@ -134,10 +301,81 @@ mod tests {
         })
         .to_string();
         let ctx: Context = serde_json::from_str(&actual).unwrap();
-        assert_eq!(ctx.language, Some("en".into()));
+        assert_eq!(ctx.language(), Some("en"));
+        assert_eq!(
+            ctx.namespace(),
+            Some("https://www.w3.org/ns/activitystreams")
+        );
+        assert!(ctx.is_activitystreams());
+    }
+
+    #[test]
+    fn string_context_round_trips() {
+        let actual = json!("https://www.w3.org/ns/activitystreams");
+        let ctx: Context = serde_json::from_value(actual.clone()).unwrap();
+        assert!(ctx.is_activitystreams());
+        assert!(ctx.extensions().is_empty());
+        assert_eq!(serde_json::to_value(&ctx).unwrap(), actual);
+    }
+
+    #[test]
+    fn mastodon_context_round_trips() {
+        let actual = json!([
+          "https://www.w3.org/ns/activitystreams",
+          "https://w3id.org/security/v1",
+          {
+            "manuallyApprovesFollowers": "as:manuallyApprovesFollowers",
+            "toot": "http://joinmastodon.org/ns#",
+            "featured": {
+              "@id": "toot:featured",
+              "@type": "@id"
+            },
+            "schema": "http://schema.org#",
+            "PropertyValue": "schema:PropertyValue",
+            "value": "schema:value",
+            "discoverable": "toot:discoverable",
+            "Emoji": "toot:Emoji"
+          }
+        ]);
+        let ctx: Context = serde_json::from_value(actual.clone()).unwrap();
+        assert_eq!(ctx.entries().len(), 3);
+        assert!(ctx.is_activitystreams());
+        assert_eq!(
+            ctx.extensions(),
+            vec![
+                "https://w3id.org/security/v1",
+                "http://schema.org#",
+                "http://joinmastodon.org/ns#"
+            ]
+        );
+        assert!(ctx.has_extension("http://joinmastodon.org/ns#"));
+        assert!(!ctx.has_extension("https://www.w3.org/ns/activitystreams"));
+        assert_eq!(ctx.term("featured").unwrap()["@id"], json!("toot:featured"));
+        assert_eq!(ctx.namespace(), None);
+        assert_eq!(serde_json::to_value(&ctx).unwrap(), actual);
+    }
+
+    #[test]
+    fn serialize_context_with_extensions() {
+        let ctx = ContextBuilder::new()
+            .term("toot", "http://joinmastodon.org/ns#")
+            .extension("https://w3id.org/security/v1")
+            .build()
+            .unwrap();
+        let expected = json!([
+          {
+            "@vocab": "https://www.w3.org/ns/activitystreams",
+            "toot": "http://joinmastodon.org/ns#"
+          },
+          "https://w3id.org/security/v1"
+        ]);
+        assert_eq!(serde_json::to_value(&ctx).unwrap(), expected);
         assert_eq!(
-            ctx.namespace,
-            "https://www.w3.org/ns/activitystreams".to_string()
+            ctx.extensions(),
+            vec![
+                "https://w3id.org/security/v1",
+                "http://joinmastodon.org/ns#"
+            ]
         );
     }
 }
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::iter::FromIterator;
+
+///////////////////////////
+// One or Many
+///////////////////////////
+/// The value of a non-functional property. Activity Streams allows any
+/// property that is not marked as functional to hold either a single value or
+/// an array of values, and producers use both shapes interchangeably. The
+/// shape that was deserialized is kept so that it is reproduced on output.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+impl<T> OneOrMany<T> {
+    pub fn len(&self) -> usize {
+        match self {
+            OneOrMany::Many(values) => values.len(),
+            OneOrMany::One(_) => 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The first value: the only one for a single value.
+    pub fn first(&self) -> Option<&T> {
+        self.as_slice().first()
+    }
+
+    pub fn into_first(self) -> Option<T> {
+        self.into_vec().into_iter().next()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        match self {
+            OneOrMany::Many(values) => values.as_slice(),
+            OneOrMany::One(value) => std::slice::from_ref(value),
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match self {
+            OneOrMany::Many(values) => values.as_mut_slice(),
+            OneOrMany::One(value) => std::slice::from_mut(value),
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::Many(values) => values,
+            OneOrMany::One(value) => vec![value],
+        }
+    }
+
+    /// Adds a value, turning a single value into an array.
+    pub fn push(&mut self, value: T) {
+        match self {
+            OneOrMany::Many(values) => values.push(value),
+            OneOrMany::One(_) => {
+                if let OneOrMany::One(first) = std::mem::replace(self, OneOrMany::Many(vec![])) {
+                    *self = OneOrMany::Many(vec![first, value]);
+                }
+            }
+        }
+    }
+}
+
+impl<T> From<T> for OneOrMany<T> {
+    fn from(value: T) -> Self {
+        OneOrMany::One(value)
+    }
+}
+
+impl From<&str> for OneOrMany<String> {
+    fn from(value: &str) -> Self {
+        OneOrMany::One(value.into())
+    }
+}
+
+impl<T> From<Vec<T>> for OneOrMany<T> {
+    fn from(values: Vec<T>) -> Self {
+        OneOrMany::Many(values)
+    }
+}
+
+impl<T> FromIterator<T> for OneOrMany<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        OneOrMany::Many(iter.into_iter().collect())
+    }
+}
+
+impl<T> IntoIterator for OneOrMany<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a OneOrMany<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn deserialize_one() {
+        let value: OneOrMany<String> = serde_json::from_value(json!("a")).unwrap();
+        assert_eq!(value, OneOrMany::One("a".into()));
+        assert_eq!(value.len(), 1);
+        assert_eq!(value.first(), Some(&"a".to_string()));
+        assert_eq!(serde_json::to_value(&value).unwrap(), json!("a"));
+    }
+
+    #[test]
+    fn deserialize_many() {
+        let value: OneOrMany<String> = serde_json::from_value(json!(["a", "b"])).unwrap();
+        assert_eq!(value, OneOrMany::Many(vec!["a".into(), "b".into()]));
+        assert_eq!(value.iter().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(serde_json::to_value(&value).unwrap(), json!(["a", "b"]));
+
+        // A single value in an array stays an array.
+        let value: OneOrMany<String> = serde_json::from_value(json!(["a"])).unwrap();
+        assert_eq!(serde_json::to_value(&value).unwrap(), json!(["a"]));
+    }
+
+    #[test]
+    fn push() {
+        let mut value = OneOrMany::One("a".to_string());
+        value.push("b".into());
+        assert_eq!(value, OneOrMany::Many(vec!["a".into(), "b".into()]));
+        assert_eq!(value.into_vec(), vec!["a".to_string(), "b".to_string()]);
+    }
+}
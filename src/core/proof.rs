@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::extension::Extensions;
+use crate::core::Context;
+
+///////////////////////////
+// Data Integrity Proof
+///////////////////////////
+/// A proof that a document was created by the owner of a key, as in
+/// [FEP-8b32](https://codeberg.org/fediverse/fep/src/branch/main/fep/8b32/fep-8b32.md)
+/// and [Verifiable Credential Data Integrity](https://www.w3.org/TR/vc-data-integrity/).
+///
+/// Timestamps are kept as strings: the proof is verified over the exact
+/// values it was created with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DataIntegrityProof {
+    #[serde(rename = "@context", skip_serializing_if = "Option::is_none")]
+    pub context: Option<Context>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    #[serde(rename = "type")]
+    pub proof_type: String,
+
+    /// Proofs of other types, e.g. from older specifications, may have
+    /// neither of the following three.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cryptosuite: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification_method: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_purpose: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_value: Option<String>,
+
+    #[serde(flatten)]
+    pub extensions: Extensions,
+}
+
+impl DataIntegrityProof {
+    /// The value of the `type` property.
+    pub const TYPE: &'static str = "DataIntegrityProof";
+
+    /// The purpose of proofs made by an actor on its own documents.
+    pub const ASSERTION_METHOD: &'static str = "assertionMethod";
+}
+
+///////////////////////////
+// Linked Data Signature
+///////////////////////////
+/// A [Linked Data Signature](https://w3c-ccg.github.io/ld-signatures/) over a
+/// document, as Mastodon attaches to the public activities it forwards. See
+/// [rsa_signature_2017](crate::integrity::rsa_signature_2017).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedDataSignature {
+    #[serde(rename = "type")]
+    pub signature_type: String,
+
+    /// The id of the key that made the signature. Signatures without one, or
+    /// without a `signatureValue`, parse but don't verify.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creator: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_value: Option<String>,
+
+    #[serde(flatten)]
+    pub extensions: Extensions,
+}
@@ -0,0 +1,608 @@
+//! Delivering activities to the inboxes of their recipients: the outbox side
+//! of server to server delivery.
+//!
+//! [Deliverer::deliver] works out which inboxes an activity goes to from its
+//! addressing and queues a [Job] for each. [Deliverer::run] sends the jobs
+//! that are due, signed with the key of the sending actor, and reschedules
+//! those that fail with exponential backoff until they're delivered or given
+//! up on as dead letters. Jobs wait in a [DeliveryQueue], e.g. a
+//! [MemoryQueue].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use http::{header, Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::cache::Cache;
+use crate::client::HttpClient;
+use crate::core::activity::Activity;
+use crate::core::{is_public, Document, ObjectOrLink, OneOrMany};
+use crate::fetch::Fetcher;
+use crate::signature::{self, SigningKey};
+use crate::webfinger::ACTIVITY_MEDIA_TYPE;
+use crate::{Error, Result};
+
+///////////////////////////
+// Jobs
+///////////////////////////
+/// The delivery of an activity to one inbox.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub inbox: String,
+    /// The id of the key to sign the delivery with.
+    pub key_id: String,
+    /// The activity, as JSON.
+    pub body: String,
+    /// How many times delivery failed.
+    pub attempts: u32,
+    pub next_attempt: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Where delivery jobs wait until they're due.
+#[async_trait]
+pub trait DeliveryQueue: Send + Sync {
+    /// Adds a job, to be sent once its `next_attempt` is due.
+    async fn push(&self, job: Job) -> Result<()>;
+
+    /// Takes the jobs that are due at `now` off the queue.
+    async fn take_due(&self, now: DateTime<Utc>) -> Result<Vec<Job>>;
+
+    /// Keeps a job that was given up on.
+    async fn dead_letter(&self, job: Job) -> Result<()>;
+
+    /// The jobs that were given up on.
+    async fn dead_letters(&self) -> Result<Vec<Job>>;
+}
+
+/// A [DeliveryQueue] in memory: jobs are lost when the process ends.
+#[derive(Debug, Default)]
+pub struct MemoryQueue {
+    pending: Mutex<Vec<Job>>,
+    dead: Mutex<Vec<Job>>,
+}
+
+impl MemoryQueue {
+    pub fn new() -> Self {
+        MemoryQueue::default()
+    }
+
+    /// The jobs waiting to be sent.
+    pub fn pending(&self) -> Vec<Job> {
+        lock(&self.pending).clone()
+    }
+}
+
+#[async_trait]
+impl DeliveryQueue for MemoryQueue {
+    async fn push(&self, job: Job) -> Result<()> {
+        lock(&self.pending).push(job);
+        Ok(())
+    }
+
+    async fn take_due(&self, now: DateTime<Utc>) -> Result<Vec<Job>> {
+        let mut pending = lock(&self.pending);
+        let (due, waiting) = pending.drain(..).partition(|job| job.next_attempt <= now);
+        *pending = waiting;
+        Ok(due)
+    }
+
+    async fn dead_letter(&self, job: Job) -> Result<()> {
+        lock(&self.dead).push(job);
+        Ok(())
+    }
+
+    async fn dead_letters(&self) -> Result<Vec<Job>> {
+        Ok(lock(&self.dead).clone())
+    }
+}
+
+///////////////////////////
+// Collections
+///////////////////////////
+/// Collections that activities may be addressed to, e.g. the followers of
+/// local actors, which are delivered to their members.
+#[async_trait]
+pub trait Collections: Send + Sync {
+    /// The ids of the members of the collection `id`, or `None` if it's not a
+    /// collection known here.
+    async fn members(&self, id: &str) -> Result<Option<Vec<String>>>;
+}
+
+#[async_trait]
+impl Collections for HashMap<String, Vec<String>> {
+    async fn members(&self, id: &str) -> Result<Option<Vec<String>>> {
+        Ok(self.get(id).cloned())
+    }
+}
+
+///////////////////////////
+// Deliverer
+///////////////////////////
+/// What [Deliverer::run] did with the jobs that were due.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Report {
+    pub delivered: usize,
+    pub retrying: usize,
+    pub dead: usize,
+}
+
+pub struct Deliverer<C, Q, F> {
+    pub client: C,
+    pub queue: Q,
+    /// The actors activities are delivered to, by id.
+    pub actors: Cache<F>,
+    /// Collections addressed activities are delivered to the members of.
+    /// Other collections, e.g. those of remote actors, aren't delivered to.
+    pub collections: Box<dyn Collections>,
+    /// The keys to sign deliveries with, by key id.
+    pub keys: HashMap<String, SigningKey>,
+    /// Whether to deliver to a server's shared inbox, where it has one,
+    /// rather than to the inbox of each of its actors.
+    pub use_shared_inbox: bool,
+    /// How many times to try a delivery before giving up on it.
+    pub max_attempts: u32,
+    /// How long to wait before the first retry. Each retry waits twice as long
+    /// as the last, up to `max_backoff`.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl<C: HttpClient, Q: DeliveryQueue, F: Fetcher> Deliverer<C, Q, F> {
+    pub fn new(client: C, queue: Q, fetcher: F) -> Self {
+        Deliverer {
+            client,
+            queue,
+            actors: Cache::new(fetcher),
+            collections: Box::new(HashMap::new()),
+            keys: HashMap::new(),
+            use_shared_inbox: true,
+            max_attempts: 10,
+            initial_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    pub fn with_key(mut self, key: SigningKey) -> Self {
+        self.keys.insert(key.key_id.clone(), key);
+        self
+    }
+
+    /// Queues the delivery of an activity, signed with the key `key_id`, to
+    /// each of its recipients. `bto` and `bcc` are removed from what's sent.
+    /// Returns the inboxes it will be delivered to.
+    pub async fn deliver(
+        &self,
+        document: &Document<Activity>,
+        key_id: &str,
+    ) -> Result<Vec<String>> {
+        if !self.keys.contains_key(key_id) {
+            return Err(Error::Validation(format!("no signing key {key_id}")));
+        }
+        let inboxes = self.recipients(&document.object).await?;
+
+        let mut body = serde_json::to_value(document)?;
+        strip_blind_recipients(&mut body);
+        if let Some(object) = body.get_mut("object") {
+            strip_blind_recipients(object);
+        }
+        let body = serde_json::to_string(&body)?;
+
+        let now = Utc::now();
+        for inbox in &inboxes {
+            let job = Job {
+                inbox: inbox.clone(),
+                key_id: key_id.to_string(),
+                body: body.clone(),
+                attempts: 0,
+                next_attempt: now,
+                last_error: None,
+            };
+            self.queue.push(job).await?;
+        }
+        Ok(inboxes)
+    }
+
+    /// The inboxes an activity is delivered to: those of the actors it's
+    /// addressed to, and of the members of the collections it's addressed to,
+    /// but not of its own actor. Recipients that can't be fetched or have no
+    /// inbox are left out. Recipients reached only through `bto` or `bcc` get
+    /// it in their own inbox: the shared inbox of their server couldn't tell
+    /// who it's for once those are stripped.
+    pub async fn recipients(&self, activity: &Activity) -> Result<Vec<String>> {
+        let object = &activity.base;
+        let addresses = |fields: &[&Option<OneOrMany<ObjectOrLink>>]| {
+            fields
+                .iter()
+                .copied()
+                .flatten()
+                .flat_map(OneOrMany::iter)
+                .filter_map(ObjectOrLink::id)
+                .filter(|address| !is_public(address))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        let visible = addresses(&[&object.to, &object.cc, &object.audience]);
+        let blind = addresses(&[&object.bto, &object.bcc]);
+
+        let mut targets: Vec<(String, bool)> = Vec::new();
+        for (addresses, blind) in [(visible, false), (blind, true)] {
+            for address in addresses {
+                match self.collections.members(&address).await? {
+                    Some(members) => targets.extend(members.into_iter().map(|m| (m, blind))),
+                    None => targets.push((address, blind)),
+                }
+            }
+        }
+        let senders: HashSet<&str> = activity
+            .actor
+            .iter()
+            .flat_map(OneOrMany::iter)
+            .filter_map(ObjectOrLink::id)
+            .collect();
+
+        let mut inboxes = Vec::new();
+        let mut seen_inboxes = HashSet::new();
+        let mut seen = HashSet::new();
+        // Visible targets come first, so anyone also addressed openly is seen
+        // before their blind address.
+        for (target, blind) in targets {
+            if senders.contains(target.as_str()) || !seen.insert(target.clone()) {
+                continue;
+            }
+            let Ok(actor) = self.actors.actor(&target).await else {
+                continue;
+            };
+            let shared_inbox = actor
+                .object
+                .endpoints
+                .and_then(|endpoints| endpoints.shared_inbox)
+                .filter(|_| self.use_shared_inbox && !blind);
+            if let Some(inbox) = shared_inbox.or(actor.object.inbox) {
+                if seen_inboxes.insert(inbox.clone()) {
+                    inboxes.push(inbox);
+                }
+            }
+        }
+        Ok(inboxes)
+    }
+
+    /// Sends the jobs that are due. See [Deliverer::run_at].
+    pub async fn run(&self) -> Result<Report> {
+        self.run_at(Utc::now()).await
+    }
+
+    /// Sends the jobs that are due at `now`. Failed jobs are queued again to
+    /// be retried later, unless they failed for good: after `max_attempts`,
+    /// or if the inbox answered with a client error other than 408 or 429.
+    pub async fn run_at(&self, now: DateTime<Utc>) -> Result<Report> {
+        let mut report = Report::default();
+        for mut job in self.queue.take_due(now).await? {
+            let err = match self.send(&job).await {
+                Ok(()) => {
+                    report.delivered += 1;
+                    continue;
+                }
+                Err(err) => err,
+            };
+            job.attempts += 1;
+            job.last_error = Some(err.to_string());
+            if is_permanent(&err) || job.attempts >= self.max_attempts {
+                self.queue.dead_letter(job).await?;
+                report.dead += 1;
+            } else {
+                let backoff = self
+                    .initial_backoff
+                    .saturating_mul(2u32.saturating_pow(job.attempts - 1))
+                    .min(self.max_backoff);
+                job.next_attempt =
+                    now + chrono::Duration::from_std(backoff).unwrap_or(chrono::Duration::MAX);
+                self.queue.push(job).await?;
+                report.retrying += 1;
+            }
+        }
+        Ok(report)
+    }
+
+    async fn send(&self, job: &Job) -> Result<()> {
+        let key = self
+            .keys
+            .get(&job.key_id)
+            .ok_or_else(|| Error::Validation(format!("no signing key {}", job.key_id)))?;
+        let request = Request::post(job.inbox.as_str())
+            .header(header::CONTENT_TYPE, ACTIVITY_MEDIA_TYPE)
+            .body(job.body.clone().into_bytes())
+            .map_err(|err| Error::Validation(format!("invalid inbox {}: {err}", job.inbox)))?;
+        let response = signature::send_signed(&self.client, request, key).await?;
+        if !response.status().is_success() {
+            return Err(Error::Http {
+                uri: job.inbox.clone(),
+                status: response.status(),
+            });
+        }
+        Ok(())
+    }
+}
+
+///////////////////////////
+// Helpers
+///////////////////////////
+fn strip_blind_recipients(object: &mut Value) {
+    if let Some(object) = object.as_object_mut() {
+        object.remove("bto");
+        object.remove("bcc");
+    }
+}
+
+/// Whether a delivery failed in a way that retrying won't fix.
+fn is_permanent(err: &Error) -> bool {
+    match err {
+        Error::Http { status, .. } => {
+            status.is_client_error()
+                && *status != StatusCode::REQUEST_TIMEOUT
+                && *status != StatusCode::TOO_MANY_REQUESTS
+        }
+        Error::Transport(_) => false,
+        _ => true,
+    }
+}
+
+fn lock(jobs: &Mutex<Vec<Job>>) -> MutexGuard<'_, Vec<Job>> {
+    jobs.lock().expect("delivery queue lock poisoned")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::MockFetcher;
+    use crate::signature::tests::PRIVATE_KEY_PEM;
+    use http::Response;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    const KEY_ID: &str = "https://local.example/users/alice#main-key";
+    const FOLLOWERS: &str = "https://local.example/users/alice/followers";
+
+    /// Answers every request with the status set for its URI, 202 by
+    /// default.
+    #[derive(Default)]
+    struct FakeServer {
+        statuses: Mutex<HashMap<String, StatusCode>>,
+        requests: Mutex<Vec<Request<Vec<u8>>>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for FakeServer {
+        async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+            let status = self
+                .statuses
+                .lock()
+                .unwrap()
+                .get(&request.uri().to_string())
+                .copied()
+                .unwrap_or(StatusCode::ACCEPTED);
+            self.requests.lock().unwrap().push(request);
+            Ok(Response::builder().status(status).body(Vec::new()).unwrap())
+        }
+    }
+
+    fn actor(id: &str, inbox: &str, shared_inbox: Option<&str>) -> Value {
+        let mut actor = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": id,
+          "type": "Person",
+          "inbox": inbox
+        });
+        if let Some(shared_inbox) = shared_inbox {
+            actor["endpoints"] = json!({ "sharedInbox": shared_inbox });
+        }
+        actor
+    }
+
+    fn deliverer() -> Deliverer<FakeServer, MemoryQueue, MockFetcher> {
+        let fetcher = MockFetcher::new();
+        for name in ["bob", "carol", "frank"] {
+            let id = format!("https://remote.example/users/{name}");
+            let actor = actor(
+                &id,
+                &format!("{id}/inbox"),
+                Some("https://remote.example/inbox"),
+            );
+            fetcher.insert(&id, &actor).unwrap();
+        }
+        for name in ["dave", "erin"] {
+            let id = format!("https://other.example/{name}");
+            fetcher
+                .insert(&id, &actor(&id, &format!("{id}/inbox"), None))
+                .unwrap();
+        }
+        let mut deliverer = Deliverer::new(FakeServer::default(), MemoryQueue::new(), fetcher)
+            .with_key(SigningKey::from_pem(KEY_ID.into(), PRIVATE_KEY_PEM).unwrap());
+        let followers = vec![
+            "https://remote.example/users/bob".to_string(),
+            "https://remote.example/users/carol".to_string(),
+            "https://other.example/dave".to_string(),
+        ];
+        deliverer.collections = Box::new(HashMap::from([(FOLLOWERS.to_string(), followers)]));
+        deliverer
+    }
+
+    fn create() -> Document<Activity> {
+        serde_json::from_value(json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": "https://local.example/activities/1",
+          "type": "Create",
+          "actor": "https://local.example/users/alice",
+          "to": ["https://www.w3.org/ns/activitystreams#Public", FOLLOWERS],
+          "cc": ["https://other.example/dave", "https://local.example/users/alice"],
+          "bcc": "https://other.example/erin",
+          "object": {
+            "id": "https://local.example/notes/1",
+            "type": "Note",
+            "bcc": "https://other.example/erin",
+            "content": "Hello"
+          }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn deliver_to_recipients() {
+        let deliverer = deliverer();
+        let inboxes = deliverer.deliver(&create(), KEY_ID).await.unwrap();
+        assert_eq!(
+            inboxes,
+            vec![
+                "https://remote.example/inbox",
+                "https://other.example/dave/inbox",
+                "https://other.example/erin/inbox",
+            ]
+        );
+
+        let report = deliverer.run().await.unwrap();
+        assert_eq!(
+            report,
+            Report {
+                delivered: 3,
+                ..Default::default()
+            }
+        );
+        let requests = deliverer.client.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let body: Value = serde_json::from_slice(requests[0].body()).unwrap();
+        assert!(body.get("bcc").is_none());
+        assert!(body["object"].get("bcc").is_none());
+        assert_eq!(body["cc"][0], "https://other.example/dave");
+        assert!(requests[0].headers().contains_key("signature"));
+        assert_eq!(
+            requests[0].headers()[header::CONTENT_TYPE],
+            ACTIVITY_MEDIA_TYPE
+        );
+    }
+
+    #[tokio::test]
+    async fn personal_inboxes() {
+        let mut deliverer = deliverer();
+        deliverer.use_shared_inbox = false;
+        let inboxes = deliverer.recipients(&create().object).await.unwrap();
+        assert_eq!(
+            inboxes,
+            vec![
+                "https://remote.example/users/bob/inbox",
+                "https://remote.example/users/carol/inbox",
+                "https://other.example/dave/inbox",
+                "https://other.example/erin/inbox",
+            ]
+        );
+
+        assert!(matches!(
+            deliverer
+                .deliver(&create(), "https://local.example/users/bob#main-key")
+                .await,
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn blind_recipients_get_personal_inboxes() {
+        let deliverer = deliverer();
+        let mut activity = create().object;
+        // Frank's server has a shared inbox, but it would never learn the
+        // activity is for him; Bob is a follower too, so it's fine for him.
+        activity.base.bcc = Some(OneOrMany::Many(vec![
+            ObjectOrLink::iri("https://remote.example/users/frank"),
+            ObjectOrLink::iri("https://remote.example/users/bob"),
+        ]));
+        let inboxes = deliverer.recipients(&activity).await.unwrap();
+        assert_eq!(
+            inboxes,
+            vec![
+                "https://remote.example/inbox",
+                "https://other.example/dave/inbox",
+                "https://remote.example/users/frank/inbox",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_with_backoff() {
+        let deliverer = deliverer();
+        deliverer.client.statuses.lock().unwrap().insert(
+            "https://remote.example/inbox".into(),
+            StatusCode::SERVICE_UNAVAILABLE,
+        );
+        deliverer.deliver(&create(), KEY_ID).await.unwrap();
+        let now = Utc::now();
+        let report = deliverer.run_at(now).await.unwrap();
+        assert_eq!(
+            report,
+            Report {
+                delivered: 2,
+                retrying: 1,
+                dead: 0
+            }
+        );
+        let pending = deliverer.queue.pending();
+        assert_eq!(pending[0].attempts, 1);
+        assert_eq!(pending[0].next_attempt, now + chrono::Duration::seconds(60));
+
+        // Not due yet.
+        assert_eq!(deliverer.run_at(now).await.unwrap(), Report::default());
+
+        let later = now + chrono::Duration::seconds(60);
+        deliverer.run_at(later).await.unwrap();
+        let pending = deliverer.queue.pending();
+        assert_eq!(pending[0].attempts, 2);
+        assert_eq!(
+            pending[0].next_attempt,
+            later + chrono::Duration::seconds(120)
+        );
+        assert!(pending[0].last_error.as_ref().unwrap().contains("503"));
+    }
+
+    #[tokio::test]
+    async fn dead_letters() {
+        let mut deliverer = deliverer();
+        deliverer.max_attempts = 2;
+        {
+            let mut statuses = deliverer.client.statuses.lock().unwrap();
+            statuses.insert(
+                "https://remote.example/inbox".into(),
+                StatusCode::BAD_GATEWAY,
+            );
+            statuses.insert("https://other.example/dave/inbox".into(), StatusCode::GONE);
+        }
+        deliverer.deliver(&create(), KEY_ID).await.unwrap();
+        let now = Utc::now();
+        let report = deliverer.run_at(now).await.unwrap();
+        assert_eq!(
+            report,
+            Report {
+                delivered: 1,
+                retrying: 1,
+                dead: 1
+            }
+        );
+        let later = now + chrono::Duration::hours(1);
+        let report = deliverer.run_at(later).await.unwrap();
+        assert_eq!(report.dead, 1);
+
+        let dead = deliverer.queue.dead_letters().await.unwrap();
+        let inboxes: Vec<&str> = dead.iter().map(|job| job.inbox.as_str()).collect();
+        assert_eq!(
+            inboxes,
+            vec![
+                "https://other.example/dave/inbox",
+                "https://remote.example/inbox"
+            ]
+        );
+        assert!(deliverer.queue.pending().is_empty());
+    }
+}
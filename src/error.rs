@@ -0,0 +1,157 @@
+use thiserror::Error;
+
+use crate::core::{
+    activity::ActivityBuilderError,
+    actor::{
+        ActorBuilderError, ApplicationBuilderError, GroupBuilderError, OrganizationBuilderError,
+        PersonBuilderError, ServiceBuilderError,
+    },
+    collection::{
+        CollectionBuilderError, CollectionPageBuilderError, OrderedCollectionBuilderError,
+        OrderedCollectionPageBuilderError,
+    },
+    object::{
+        ArticleBuilderError, AudioBuilderError, EventBuilderError, ImageBuilderError,
+        LinkBuilderError, NoteBuilderError, ObjectBuilderError, PageBuilderError,
+        PlaceBuilderError, PreviewBuilderError, ProfileBuilderError, RelationshipBuilderError,
+        TombstoneBuilderError, UnexpectedType, VideoBuilderError,
+    },
+    DocumentBuilderError,
+};
+
+///////////////////////////
+// Error
+///////////////////////////
+/// Everything that can go wrong in this crate. Receiving a document from the
+/// network must never panic: malformed input surfaces as [Error::Parse] or
+/// [Error::Validation] instead.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A builder was missing a field or rejected a value.
+    #[error("failed to build value: {0}")]
+    Builder(String),
+
+    /// The input is not valid JSON, or doesn't have the shape of the type it
+    /// was deserialized into.
+    #[error("failed to parse document: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    /// The input is well-formed but breaks a rule of the vocabulary, e.g. it
+    /// has the wrong `type`.
+    #[error("invalid document: {0}")]
+    Validation(String),
+
+    /// A signature could not be created or did not verify.
+    #[error("signature error: {0}")]
+    Signature(String),
+
+    /// A request could not be sent or its response could not be read.
+    #[error("request failed: {0}")]
+    Transport(String),
+
+    /// An [ObjectStore](crate::store::ObjectStore) failed to read or write.
+    #[error("storage error: {0}")]
+    Storage(String),
+
+    /// A remote server answered a request with an unsuccessful status.
+    #[error("{uri} responded with {status}")]
+    Http {
+        uri: String,
+        status: http::StatusCode,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl From<UnexpectedType> for Error {
+    fn from(err: UnexpectedType) -> Self {
+        Error::Validation(err.to_string())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::Storage(err.to_string())
+    }
+}
+
+macro_rules! builder_errors {
+    ($($name:ty),+ $(,)?) => { $(
+        impl From<$name> for Error {
+            fn from(err: $name) -> Self {
+                Error::Builder(err.to_string())
+            }
+        }
+    )+ };
+}
+
+builder_errors!(
+    ActivityBuilderError,
+    ActorBuilderError,
+    ApplicationBuilderError,
+    GroupBuilderError,
+    OrganizationBuilderError,
+    PersonBuilderError,
+    ServiceBuilderError,
+    CollectionBuilderError,
+    CollectionPageBuilderError,
+    DocumentBuilderError,
+    OrderedCollectionBuilderError,
+    OrderedCollectionPageBuilderError,
+    ArticleBuilderError,
+    AudioBuilderError,
+    EventBuilderError,
+    ImageBuilderError,
+    LinkBuilderError,
+    NoteBuilderError,
+    ObjectBuilderError,
+    PageBuilderError,
+    PlaceBuilderError,
+    PreviewBuilderError,
+    ProfileBuilderError,
+    RelationshipBuilderError,
+    TombstoneBuilderError,
+    VideoBuilderError,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{actor::Person, collection::CollectionBuilder, object::Object, Document};
+    use serde_json::json;
+
+    #[test]
+    fn parse_error() {
+        let err = Document::<Person>::deserialize_string("{\"type\":".into()).unwrap_err();
+        assert!(matches!(err, Error::Parse(_)));
+    }
+
+    #[test]
+    fn builder_error() {
+        let err: Error = ObjectBuilderError::UninitializedField("id").into();
+        assert!(matches!(err, Error::Builder(_)));
+        assert_eq!(
+            err.to_string(),
+            "failed to build value: `id` must be initialized"
+        );
+
+        let err: Error = CollectionBuilder::<Object>::default()
+            .build()
+            .unwrap_err()
+            .into();
+        assert!(matches!(err, Error::Builder(_)));
+    }
+
+    #[test]
+    fn unexpected_type_is_a_validation_error() {
+        let object = serde_json::from_value(json!({ "type": "Note" })).unwrap();
+        let err: Error = Person::try_from(crate::core::actor::Actor {
+            base: object,
+            ..Default::default()
+        })
+        .unwrap_err()
+        .into();
+        assert!(matches!(err, Error::Validation(_)));
+    }
+}
@@ -0,0 +1,720 @@
+//! Dereferencing: fetching the remote object an IRI refers to, e.g. the
+//! `actor` of an incoming activity.
+//!
+//! A [Fetcher] turns a URI into a [Document]. [HttpFetcher] fetches over any
+//! [HttpClient], signing its requests for servers that require authorized
+//! fetch and checking that what it gets back is really the object at that URI.
+//! [MockFetcher] serves documents from memory, for tests.
+//!
+//! Fetchers don't cache: wrap one in a [Cache](crate::cache::Cache) for that.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use http::{header, uri::Scheme, Request, StatusCode, Uri};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::client::HttpClient;
+use crate::core::{Document, ObjectOrLink};
+use crate::signature::{cavage, SigningKey};
+use crate::{Error, Result};
+
+/// The `Accept` header of dereferencing requests.
+pub const ACCEPT: &str =
+    "application/activity+json, application/ld+json; profile=\"https://www.w3.org/ns/activitystreams\"";
+
+///////////////////////////
+// Fetcher
+///////////////////////////
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    /// The JSON of the object at `uri`, as it was received: verify signatures
+    /// embedded in the object over this rather than over a [Document].
+    async fn fetch_json(&self, uri: &str) -> Result<Value>;
+
+    /// The object at `uri`.
+    async fn fetch<T: DeserializeOwned>(&self, uri: &str) -> Result<Document<T>> {
+        Ok(serde_json::from_value(self.fetch_json(uri).await?)?)
+    }
+
+    /// Fetches the object at `uri` unless it still has the entity tag `etag`,
+    /// along with how it may be cached. Fetchers that can't revalidate always
+    /// fetch the object.
+    async fn fetch_conditional(&self, uri: &str, _etag: Option<&str>) -> Result<Fetched> {
+        Ok(Fetched {
+            document: Some(self.fetch_json(uri).await?),
+            ..Default::default()
+        })
+    }
+}
+
+/// The result of [Fetcher::fetch_conditional].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fetched {
+    /// The JSON of the object, or `None` if it still has the entity tag it
+    /// was revalidated with.
+    pub document: Option<Value>,
+    pub etag: Option<String>,
+    pub cache_control: CacheControl,
+}
+
+/// The directives of a `Cache-Control` response header that matter to a
+/// client side cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    pub max_age: Option<Duration>,
+    /// The response may be cached, but must be revalidated before it's used.
+    pub no_cache: bool,
+    /// The response must not be cached.
+    pub no_store: bool,
+}
+
+impl CacheControl {
+    pub fn parse(header: &str) -> Self {
+        let mut cache_control = CacheControl::default();
+        for directive in header.split(',') {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            match (name.to_ascii_lowercase().as_str(), value) {
+                ("max-age", Some(seconds)) => {
+                    cache_control.max_age = seconds.parse().ok().map(Duration::from_secs);
+                }
+                ("no-cache", _) => cache_control.no_cache = true,
+                ("no-store", _) => cache_control.no_store = true,
+                _ => {}
+            }
+        }
+        cache_control
+    }
+}
+
+/// The object a property refers to: the embedded object itself, or else the
+/// object fetched from its IRI or the `href` of its link.
+pub async fn resolve<T, F>(fetcher: &F, value: &ObjectOrLink<T>) -> Result<T>
+where
+    T: DeserializeOwned + Serialize + Clone + Send + Sync,
+    F: Fetcher + ?Sized,
+{
+    let uri = match value {
+        ObjectOrLink::Object(object) => return Ok(object.as_ref().clone()),
+        ObjectOrLink::Iri(iri) => iri.as_str(),
+        ObjectOrLink::Link(link) => link.href.as_str(),
+    };
+    Ok(fetcher.fetch::<T>(uri).await?.object)
+}
+
+///////////////////////////
+// HTTP
+///////////////////////////
+/// Fetches objects over HTTP, following a limited number of redirects.
+///
+/// A fetched object must have an `id` on the same origin as the URI it was
+/// finally served from, so that a server can't pass off objects of another.
+#[derive(Debug, Clone)]
+pub struct HttpFetcher<C> {
+    pub client: C,
+    /// The key of a local actor to sign requests with, for servers that
+    /// require authorized fetch.
+    pub signing_key: Option<SigningKey>,
+    /// How many redirects to follow.
+    pub max_redirects: usize,
+    /// The largest response body to accept, in bytes. It's enforced while
+    /// the body is read, see [HttpClient::execute_limited].
+    pub max_body_size: usize,
+}
+
+impl<C: HttpClient> HttpFetcher<C> {
+    pub fn new(client: C) -> Self {
+        HttpFetcher {
+            client,
+            signing_key: None,
+            max_redirects: 5,
+            max_body_size: 1024 * 1024,
+        }
+    }
+
+    pub fn with_signing_key(mut self, key: SigningKey) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
+    fn request(&self, uri: &Uri, etag: Option<&str>) -> Result<Request<Vec<u8>>> {
+        let mut request = Request::get(uri.clone()).header(header::ACCEPT, ACCEPT);
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        let mut request = request
+            .body(Vec::new())
+            .map_err(|err| Error::Validation(format!("invalid request to {uri}: {err}")))?;
+        if let Some(key) = &self.signing_key {
+            cavage::sign(&mut request, key)?;
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl HttpFetcher<reqwest::Client> {
+    /// A fetcher using [reqwest_client](crate::client::reqwest_client).
+    pub fn reqwest() -> Result<Self> {
+        Ok(HttpFetcher::new(crate::client::reqwest_client()?))
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> Fetcher for HttpFetcher<C> {
+    async fn fetch_json(&self, uri: &str) -> Result<Value> {
+        Ok(self
+            .fetch_conditional(uri, None)
+            .await?
+            .document
+            .expect("unconditional fetches return a document"))
+    }
+
+    async fn fetch_conditional(&self, uri: &str, etag: Option<&str>) -> Result<Fetched> {
+        let mut current = parse_uri(uri)?;
+        for _ in 0..=self.max_redirects {
+            let request = self.request(&current, etag)?;
+            let response = self
+                .client
+                .execute_limited(request, self.max_body_size)
+                .await?;
+            let status = response.status();
+            let headers = response.headers();
+            let cache_control = headers
+                .get(header::CACHE_CONTROL)
+                .and_then(|cache_control| cache_control.to_str().ok())
+                .map(CacheControl::parse)
+                .unwrap_or_default();
+            let response_etag = headers
+                .get(header::ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .map(String::from);
+            if status == StatusCode::NOT_MODIFIED && etag.is_some() {
+                return Ok(Fetched {
+                    document: None,
+                    etag: response_etag.or_else(|| etag.map(String::from)),
+                    cache_control,
+                });
+            }
+            if status.is_redirection() && status != StatusCode::NOT_MODIFIED {
+                let location = response
+                    .headers()
+                    .get(header::LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .ok_or_else(|| {
+                        Error::Validation(format!("redirect from {current} has no location"))
+                    })?;
+                current = redirect_target(&current, location)?;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(Error::Http {
+                    uri: current.to_string(),
+                    status,
+                });
+            }
+
+            let content_type = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .unwrap_or_default();
+            if !is_json(content_type) {
+                return Err(Error::Validation(format!(
+                    "{current} is not an ActivityStreams document: {content_type}"
+                )));
+            }
+            let document: Value = serde_json::from_slice(response.body())?;
+            check_origin(&document, &current)?;
+            return Ok(Fetched {
+                document: Some(document),
+                etag: response_etag,
+                cache_control,
+            });
+        }
+        Err(Error::Validation(format!(
+            "too many redirects fetching {uri}"
+        )))
+    }
+}
+
+fn parse_uri(uri: &str) -> Result<Uri> {
+    let parsed: Uri = uri
+        .parse()
+        .map_err(|_| Error::Validation(format!("invalid URI {uri}")))?;
+    match parsed.scheme() {
+        Some(scheme) if *scheme == Scheme::HTTPS || *scheme == Scheme::HTTP => {}
+        _ => return Err(Error::Validation(format!("can't fetch {uri}"))),
+    }
+    if parsed.host().is_none() {
+        return Err(Error::Validation(format!("invalid URI {uri}")));
+    }
+    Ok(parsed)
+}
+
+/// The absolute URI a `Location` header points to.
+fn redirect_target(current: &Uri, location: &str) -> Result<Uri> {
+    if location.starts_with('/') && !location.starts_with("//") {
+        let scheme = current.scheme_str().unwrap_or("https");
+        let authority = current.authority().map(|a| a.as_str()).unwrap_or_default();
+        parse_uri(&format!("{scheme}://{authority}{location}"))
+    } else {
+        parse_uri(location)
+    }
+}
+
+fn is_json(content_type: &str) -> bool {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    matches!(
+        media_type.as_str(),
+        "application/activity+json" | "application/ld+json" | "application/json"
+    )
+}
+
+/// Checks that `document` has an `id` on the origin of `uri`.
+fn check_origin(document: &Value, uri: &Uri) -> Result<()> {
+    let id = document
+        .get("id")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::Validation(format!("{uri} has no id")))?;
+    let id_uri = parse_uri(id)?;
+    if origin(&id_uri) != origin(uri) {
+        return Err(Error::Validation(format!(
+            "{uri} served {id} from another origin"
+        )));
+    }
+    Ok(())
+}
+
+/// Whether two URIs have the same scheme, host and port.
+pub(crate) fn same_origin(a: &str, b: &str) -> bool {
+    match (parse_uri(a), parse_uri(b)) {
+        (Ok(a), Ok(b)) => origin(&a) == origin(&b),
+        _ => false,
+    }
+}
+
+fn origin(uri: &Uri) -> (String, String, Option<u16>) {
+    let scheme = uri.scheme_str().unwrap_or_default().to_ascii_lowercase();
+    let port = uri.port_u16().or(match scheme.as_str() {
+        "https" => Some(443),
+        "http" => Some(80),
+        _ => None,
+    });
+    let host = uri.host().unwrap_or_default().to_ascii_lowercase();
+    (scheme, host, port)
+}
+
+///////////////////////////
+// Mock
+///////////////////////////
+/// A [Fetcher] serving documents from memory, which records the URIs it was
+/// asked for. URIs it has no document for are 404s.
+#[derive(Debug, Default)]
+pub struct MockFetcher {
+    documents: Mutex<HashMap<String, MockResponse>>,
+    requests: Mutex<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+enum MockResponse {
+    Document(Value, Option<String>),
+    Status(StatusCode),
+}
+
+impl MockFetcher {
+    pub fn new() -> Self {
+        MockFetcher::default()
+    }
+
+    /// Serves `document` at `uri`.
+    pub fn insert<T: Serialize>(&self, uri: &str, document: &T) -> Result<()> {
+        let document = serde_json::to_value(document)?;
+        self.lock_documents()
+            .insert(uri.to_string(), MockResponse::Document(document, None));
+        Ok(())
+    }
+
+    /// Serves `document` at `uri` with an entity tag, so that it can be
+    /// revalidated.
+    pub fn insert_with_etag<T: Serialize>(
+        &self,
+        uri: &str,
+        document: &T,
+        etag: &str,
+    ) -> Result<()> {
+        let document = serde_json::to_value(document)?;
+        self.lock_documents().insert(
+            uri.to_string(),
+            MockResponse::Document(document, Some(etag.to_string())),
+        );
+        Ok(())
+    }
+
+    /// Answers requests for `uri` with `status`, e.g. 410 for a deleted
+    /// object.
+    pub fn insert_status(&self, uri: &str, status: StatusCode) {
+        self.lock_documents()
+            .insert(uri.to_string(), MockResponse::Status(status));
+    }
+
+    pub fn remove(&self, uri: &str) {
+        self.lock_documents().remove(uri);
+    }
+
+    /// The URIs fetched so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .expect("mock fetcher lock poisoned")
+            .clone()
+    }
+
+    fn lock_documents(&self) -> std::sync::MutexGuard<'_, HashMap<String, MockResponse>> {
+        self.documents.lock().expect("mock fetcher lock poisoned")
+    }
+}
+
+#[async_trait]
+impl Fetcher for MockFetcher {
+    async fn fetch_json(&self, uri: &str) -> Result<Value> {
+        Ok(self
+            .fetch_conditional(uri, None)
+            .await?
+            .document
+            .expect("unconditional fetches return a document"))
+    }
+
+    async fn fetch_conditional(&self, uri: &str, etag: Option<&str>) -> Result<Fetched> {
+        self.requests
+            .lock()
+            .expect("mock fetcher lock poisoned")
+            .push(uri.to_string());
+        match self.lock_documents().get(uri) {
+            Some(MockResponse::Document(document, current)) => Ok(Fetched {
+                document: (etag.is_none() || etag != current.as_deref()).then(|| document.clone()),
+                etag: current.clone(),
+                cache_control: CacheControl::default(),
+            }),
+            Some(MockResponse::Status(status)) => Err(Error::Http {
+                uri: uri.to_string(),
+                status: *status,
+            }),
+            None => Err(Error::Http {
+                uri: uri.to_string(),
+                status: StatusCode::NOT_FOUND,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::activity::Activity;
+    use crate::core::actor::Person;
+    use crate::signature::tests::PRIVATE_KEY_PEM;
+    use http::Response;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn alice() -> Value {
+        json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": "https://example.social/users/alice",
+          "type": "Person",
+          "preferredUsername": "alice",
+          "inbox": "https://example.social/users/alice/inbox"
+        })
+    }
+
+    /// Answers with the responses queued for each URI, recording requests.
+    #[derive(Default)]
+    struct FakeServer {
+        responses: Mutex<HashMap<String, Response<Vec<u8>>>>,
+        requests: Mutex<Vec<Request<Vec<u8>>>>,
+    }
+
+    impl FakeServer {
+        fn respond(self, uri: &str, response: http::response::Builder, body: Vec<u8>) -> Self {
+            self.responses
+                .lock()
+                .unwrap()
+                .insert(uri.into(), response.body(body).unwrap());
+            self
+        }
+
+        fn json(self, uri: &str, document: Value) -> Self {
+            let response = Response::builder().header(
+                header::CONTENT_TYPE,
+                "application/activity+json; charset=utf-8",
+            );
+            self.respond(uri, response, serde_json::to_vec(&document).unwrap())
+        }
+
+        fn redirect(self, from: &str, to: &str) -> Self {
+            let response = Response::builder()
+                .status(StatusCode::FOUND)
+                .header(header::LOCATION, to);
+            self.respond(from, response, Vec::new())
+        }
+    }
+
+    #[async_trait]
+    impl HttpClient for FakeServer {
+        async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+            let uri = request.uri().to_string();
+            self.requests.lock().unwrap().push(request);
+            let mut responses = self.responses.lock().unwrap();
+            Ok(responses.remove(&uri).unwrap_or_else(|| {
+                Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Vec::new())
+                    .unwrap()
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_actor() {
+        let server = FakeServer::default().json("https://example.social/users/alice", alice());
+        let fetcher = HttpFetcher::new(server);
+        let person: Document<Person> = fetcher
+            .fetch("https://example.social/users/alice")
+            .await
+            .unwrap();
+        assert_eq!(
+            person.object.base.preferred_username.as_deref(),
+            Some("alice")
+        );
+
+        let requests = fetcher.client.requests.lock().unwrap();
+        assert_eq!(requests[0].headers()[header::ACCEPT], ACCEPT);
+        assert!(!requests[0].headers().contains_key("signature"));
+    }
+
+    #[tokio::test]
+    async fn authorized_fetch() {
+        let server = FakeServer::default().json("https://example.social/users/alice", alice());
+        let key = SigningKey::from_pem(
+            "https://local.example/actor#main-key".into(),
+            PRIVATE_KEY_PEM,
+        )
+        .unwrap();
+        let fetcher = HttpFetcher::new(server).with_signing_key(key);
+        fetcher
+            .fetch_json("https://example.social/users/alice")
+            .await
+            .unwrap();
+        let requests = fetcher.client.requests.lock().unwrap();
+        let signature = requests[0].headers()["signature"].to_str().unwrap();
+        assert!(signature.contains("keyId=\"https://local.example/actor#main-key\""));
+    }
+
+    #[tokio::test]
+    async fn redirects() {
+        let server = FakeServer::default()
+            .redirect("https://example.social/@alice", "/users/alice")
+            .json("https://example.social/users/alice", alice());
+        let fetcher = HttpFetcher::new(server);
+        let document = fetcher
+            .fetch_json("https://example.social/@alice")
+            .await
+            .unwrap();
+        assert_eq!(document["id"], "https://example.social/users/alice");
+
+        let mut server = FakeServer::default();
+        for i in 0..3 {
+            server = server.redirect(
+                &format!("https://example.social/{i}"),
+                &format!("https://example.social/{}", i + 1),
+            );
+        }
+        let mut fetcher = HttpFetcher::new(server);
+        fetcher.max_redirects = 2;
+        assert!(matches!(
+            fetcher.fetch_json("https://example.social/0").await,
+            Err(Error::Validation(message)) if message.starts_with("too many redirects")
+        ));
+    }
+
+    #[tokio::test]
+    async fn origin_checks() {
+        // A server can't serve an object claiming to be another server's.
+        let server = FakeServer::default().json("https://evil.example/users/alice", alice());
+        let fetcher = HttpFetcher::new(server);
+        assert!(fetcher
+            .fetch_json("https://evil.example/users/alice")
+            .await
+            .is_err());
+
+        // Nor redirect to one.
+        let server = FakeServer::default()
+            .redirect(
+                "https://example.social/users/alice",
+                "https://evil.example/alice",
+            )
+            .json("https://evil.example/alice", alice());
+        let fetcher = HttpFetcher::new(server);
+        assert!(fetcher
+            .fetch_json("https://example.social/users/alice")
+            .await
+            .is_err());
+
+        let fetcher = HttpFetcher::new(FakeServer::default());
+        assert!(matches!(
+            fetcher.fetch_json("ftp://example.social/users/alice").await,
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejected_responses() {
+        let html = Response::builder().header(header::CONTENT_TYPE, "text/html");
+        let server = FakeServer::default()
+            .respond(
+                "https://example.social/page",
+                html,
+                b"<html></html>".to_vec(),
+            )
+            .json("https://example.social/users/alice", alice());
+        let mut fetcher = HttpFetcher::new(server);
+        assert!(matches!(
+            fetcher.fetch_json("https://example.social/page").await,
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            fetcher.fetch_json("https://example.social/missing").await,
+            Err(Error::Http {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })
+        ));
+
+        fetcher.max_body_size = 16;
+        assert!(matches!(
+            fetcher.fetch_json("https://example.social/users/alice").await,
+            Err(Error::Validation(message)) if message.contains("larger than")
+        ));
+    }
+
+    #[tokio::test]
+    async fn conditional_fetch() {
+        let response = Response::builder()
+            .header(header::CONTENT_TYPE, "application/activity+json")
+            .header(header::ETAG, "\"v1\"")
+            .header(header::CACHE_CONTROL, "public, max-age=180");
+        let not_modified = Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::CACHE_CONTROL, "max-age=60");
+        let server = FakeServer::default().respond(
+            "https://example.social/users/alice",
+            response,
+            serde_json::to_vec(&alice()).unwrap(),
+        );
+        let fetcher = HttpFetcher::new(server);
+        let fetched = fetcher
+            .fetch_conditional("https://example.social/users/alice", None)
+            .await
+            .unwrap();
+        assert_eq!(fetched.document, Some(alice()));
+        assert_eq!(fetched.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(
+            fetched.cache_control.max_age,
+            Some(Duration::from_secs(180))
+        );
+
+        let fetcher = HttpFetcher::new(FakeServer::default().respond(
+            "https://example.social/users/alice",
+            not_modified,
+            Vec::new(),
+        ));
+        let fetched = fetcher
+            .fetch_conditional("https://example.social/users/alice", Some("\"v1\""))
+            .await
+            .unwrap();
+        assert_eq!(
+            fetched,
+            Fetched {
+                document: None,
+                etag: Some("\"v1\"".into()),
+                cache_control: CacheControl {
+                    max_age: Some(Duration::from_secs(60)),
+                    ..Default::default()
+                },
+            }
+        );
+        let requests = fetcher.client.requests.lock().unwrap();
+        assert_eq!(requests[0].headers()[header::IF_NONE_MATCH], "\"v1\"");
+    }
+
+    #[test]
+    fn parse_cache_control() {
+        assert_eq!(
+            CacheControl::parse("max-age=300, must-revalidate"),
+            CacheControl {
+                max_age: Some(Duration::from_secs(300)),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            CacheControl::parse("No-Cache, no-store"),
+            CacheControl {
+                max_age: None,
+                no_cache: true,
+                no_store: true,
+            }
+        );
+        assert_eq!(CacheControl::parse("max-age=soon"), CacheControl::default());
+    }
+
+    #[tokio::test]
+    async fn mock_fetcher() {
+        let fetcher = MockFetcher::new();
+        fetcher
+            .insert("https://example.social/users/alice", &alice())
+            .unwrap();
+        fetcher.insert_status("https://example.social/users/bob", StatusCode::GONE);
+
+        let activity: Document<Activity> = serde_json::from_value(json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "type": "Follow",
+          "actor": "https://example.social/users/alice",
+          "object": "https://example.social/users/bob"
+        }))
+        .unwrap();
+        let actor = activity.object.actor.as_ref().unwrap().first().unwrap();
+        let actor = resolve(&fetcher, actor).await.unwrap();
+        assert_eq!(
+            actor.inbox.as_deref(),
+            Some("https://example.social/users/alice/inbox")
+        );
+
+        let object = activity.object.object.as_ref().unwrap().first().unwrap();
+        assert!(matches!(
+            resolve(&fetcher, object).await,
+            Err(Error::Http {
+                status: StatusCode::GONE,
+                ..
+            })
+        ));
+        assert_eq!(
+            fetcher.requests(),
+            vec![
+                "https://example.social/users/alice",
+                "https://example.social/users/bob"
+            ]
+        );
+    }
+}
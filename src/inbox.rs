@@ -0,0 +1,726 @@
+//! Receiving activities from other servers: the inbox side of server to
+//! server delivery.
+//!
+//! [Inbox::handle] takes the `POST` a remote server delivered, verifies its
+//! HTTP signature with the key of the actor that signed it and passes the
+//! activity on to [Inbox::receive]. That validates the activity, drops
+//! duplicates and dispatches it by type to an [InboxHandler]. Neither depends
+//! on a web framework: they work on [http] requests and responses.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use http::{header, Method, Request, Response, StatusCode};
+
+use crate::cache::Cache;
+use crate::core::activity::{Activity, ActivityType};
+use crate::core::actor::{Actor, PublicKeyInfo};
+use crate::core::Document;
+use crate::fetch::{same_origin, Fetcher};
+use crate::signature;
+use crate::{Error, Result};
+
+///////////////////////////
+// Handler
+///////////////////////////
+/// What to do with received activities.
+///
+/// Each method is called with a validated activity and the actor that sent
+/// it. By default they all pass the activity on to
+/// [on_activity](InboxHandler::on_activity), which ignores it. An error makes
+/// the delivery fail, so that the sender retries it.
+#[async_trait]
+pub trait InboxHandler: Send + Sync {
+    async fn on_accept(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_add(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_announce(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_block(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_create(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_delete(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_flag(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_follow(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_like(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_move(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_reject(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_remove(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_undo(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    async fn on_update(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        self.on_activity(activity, actor).await
+    }
+
+    /// Any activity without a method of its own, including extension types.
+    async fn on_activity(&self, _activity: &Activity, _actor: &Actor) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// What became of a received activity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Received {
+    /// The activity was passed to the handler.
+    Handled,
+    /// The activity was received before, and dropped.
+    Duplicate,
+}
+
+///////////////////////////
+// Inbox
+///////////////////////////
+pub struct Inbox<H, F> {
+    pub handler: H,
+    /// The actors that sign deliveries, by id.
+    pub actors: Cache<F>,
+    /// The largest request body to accept, in bytes.
+    pub max_body_size: usize,
+    /// How many activity ids to remember, to drop activities delivered twice.
+    pub max_seen: usize,
+    /// How long ago an actor must have been fetched for a signature its key
+    /// doesn't verify to make it fetched again, in case the key changed.
+    pub min_refetch_age: Duration,
+    seen: Mutex<Seen>,
+}
+
+#[derive(Debug, Default)]
+struct Seen {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl<H: InboxHandler, F: Fetcher> Inbox<H, F> {
+    pub fn new(handler: H, fetcher: F) -> Self {
+        Inbox {
+            handler,
+            actors: Cache::new(fetcher),
+            max_body_size: 1024 * 1024,
+            max_seen: 10_000,
+            min_refetch_age: Duration::from_secs(60),
+            seen: Mutex::default(),
+        }
+    }
+
+    /// Handles a delivery to the inbox, answering `202 Accepted` once the
+    /// activity is handled or found to be a duplicate.
+    pub async fn handle(&self, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+        if request.method() != Method::POST {
+            return respond(StatusCode::METHOD_NOT_ALLOWED, "inboxes only accept POST");
+        }
+        let content_type = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .unwrap_or_default();
+        if !is_activity(content_type) {
+            return respond(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                &format!("not an activity: {content_type}"),
+            );
+        }
+        if request.body().len() > self.max_body_size {
+            return respond(StatusCode::PAYLOAD_TOO_LARGE, "activity is too large");
+        }
+
+        let actor = match self.verify(&request).await {
+            Ok(actor) => actor,
+            Err(err) => return respond(StatusCode::UNAUTHORIZED, &err.to_string()),
+        };
+        let document = match Document::<Activity>::deserialize_slice(request.body()) {
+            Ok(document) => document,
+            Err(err) => return respond(StatusCode::BAD_REQUEST, &err.to_string()),
+        };
+        match self.receive(document, &actor).await {
+            Ok(_) => respond(StatusCode::ACCEPTED, ""),
+            Err(err @ (Error::Validation(_) | Error::Parse(_))) => {
+                respond(StatusCode::BAD_REQUEST, &err.to_string())
+            }
+            Err(err @ Error::Signature(_)) => respond(StatusCode::FORBIDDEN, &err.to_string()),
+            Err(err) => respond(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+        }
+    }
+
+    /// Verifies the HTTP signature of a request, returning the actor that
+    /// signed it.
+    pub async fn verify(&self, request: &Request<Vec<u8>>) -> Result<Actor> {
+        let key_id = signature::key_id(request)?;
+        let (actor, key) = self.signer(&key_id).await?;
+        let err = match signature::verify(request, &key) {
+            Ok(()) => return Ok(actor),
+            Err(err) => err,
+        };
+
+        // The actor may have changed its key since it was cached, but bad
+        // signatures mustn't make it fetched again and again.
+        let stale = self
+            .actors
+            .age(document_id(&key_id))
+            .is_some_and(|age| age >= self.min_refetch_age);
+        if !stale {
+            return Err(err);
+        }
+        if let Some(id) = &actor.base.id {
+            self.actors.invalidate(id);
+        }
+        self.actors.invalidate(document_id(&key_id));
+        let (actor, key) = self.signer(&key_id).await?;
+        signature::verify(request, &key)?;
+        Ok(actor)
+    }
+
+    /// Validates an activity delivered by `actor`, whose signature has been
+    /// verified, and dispatches it to the handler unless it's a duplicate.
+    pub async fn receive(&self, document: Document<Activity>, actor: &Actor) -> Result<Received> {
+        let activity = document.object;
+        let id = validate(&activity, actor)?;
+        if !self.remember(&id) {
+            return Ok(Received::Duplicate);
+        }
+        if let Err(err) = self.dispatch(&activity, actor).await {
+            // Let the sender retry.
+            self.forget(&id);
+            return Err(err);
+        }
+        Ok(Received::Handled)
+    }
+
+    async fn dispatch(&self, activity: &Activity, actor: &Actor) -> Result<()> {
+        let handler = &self.handler;
+        match activity
+            .activity_type()
+            .unwrap_or(ActivityType::Other(String::new()))
+        {
+            ActivityType::Accept => handler.on_accept(activity, actor).await,
+            ActivityType::Add => handler.on_add(activity, actor).await,
+            ActivityType::Announce => handler.on_announce(activity, actor).await,
+            ActivityType::Block => handler.on_block(activity, actor).await,
+            ActivityType::Create => handler.on_create(activity, actor).await,
+            ActivityType::Delete => handler.on_delete(activity, actor).await,
+            ActivityType::Flag => handler.on_flag(activity, actor).await,
+            ActivityType::Follow => handler.on_follow(activity, actor).await,
+            ActivityType::Like => handler.on_like(activity, actor).await,
+            ActivityType::Move => handler.on_move(activity, actor).await,
+            ActivityType::Reject => handler.on_reject(activity, actor).await,
+            ActivityType::Remove => handler.on_remove(activity, actor).await,
+            ActivityType::Undo => handler.on_undo(activity, actor).await,
+            ActivityType::Update => handler.on_update(activity, actor).await,
+            _ => handler.on_activity(activity, actor).await,
+        }
+    }
+
+    /// The actor publishing the key `key_id`, and the key.
+    async fn signer(&self, key_id: &str) -> Result<(Actor, PublicKeyInfo)> {
+        let actor = self.actors.actor(document_id(key_id)).await?.object;
+        if let Some(key) = actor.find_key(key_id) {
+            let key = key.to_public_key_info()?;
+            return Ok((actor, key));
+        }
+
+        // The key is published apart from its owner, which must claim it.
+        let owner_id = actor
+            .keys()
+            .into_iter()
+            .find(|key| key.id() == key_id)
+            .map(|key| key.controller().to_string())
+            .ok_or_else(|| Error::Signature(format!("no key {key_id}")))?;
+        let owner = self.actors.actor(&owner_id).await?.object;
+        if owner.base.id.as_deref() != Some(owner_id.as_str()) {
+            return Err(Error::Signature(format!(
+                "{owner_id} doesn't own the key {key_id}"
+            )));
+        }
+        let key = owner
+            .find_key(key_id)
+            .ok_or_else(|| Error::Signature(format!("{owner_id} doesn't own the key {key_id}")))?
+            .to_public_key_info()?;
+        Ok((owner, key))
+    }
+
+    /// Records that an activity was received, returning whether it's new.
+    fn remember(&self, id: &str) -> bool {
+        let mut seen = self.lock_seen();
+        if !seen.ids.insert(id.to_string()) {
+            return false;
+        }
+        seen.order.push_back(id.to_string());
+        while seen.order.len() > self.max_seen {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    fn forget(&self, id: &str) {
+        let mut seen = self.lock_seen();
+        seen.ids.remove(id);
+        seen.order.retain(|seen| seen != id);
+    }
+
+    fn lock_seen(&self) -> MutexGuard<'_, Seen> {
+        self.seen.lock().expect("inbox lock poisoned")
+    }
+}
+
+///////////////////////////
+// Validation
+///////////////////////////
+/// Checks that an activity delivered by `actor` is well formed and really by
+/// that actor, returning its id.
+pub fn validate(activity: &Activity, actor: &Actor) -> Result<String> {
+    let actor_id = actor
+        .base
+        .id
+        .as_deref()
+        .ok_or_else(|| Error::Validation("actor has no id".into()))?;
+    let id = activity
+        .base
+        .id
+        .clone()
+        .ok_or_else(|| Error::Validation("activity has no id".into()))?;
+    let activity_type = activity
+        .activity_type()
+        .ok_or_else(|| Error::Validation(format!("activity {id} has no type")))?;
+
+    let actors = activity
+        .actor
+        .as_ref()
+        .ok_or_else(|| Error::Validation(format!("activity {id} has no actor")))?;
+    for claimed in actors.iter() {
+        let claimed = claimed.id().unwrap_or_default();
+        if claimed != actor_id {
+            return Err(Error::Signature(format!(
+                "activity by {claimed} was signed by {actor_id}"
+            )));
+        }
+    }
+    if !same_origin(&id, actor_id) {
+        return Err(Error::Validation(format!(
+            "activity {id} isn't on the origin of {actor_id}"
+        )));
+    }
+
+    let intransitive =
+        activity_type.is_intransitive() || matches!(activity_type, ActivityType::Other(_));
+    if !intransitive && activity.object.is_none() {
+        return Err(Error::Validation(format!(
+            "{activity_type} {id} has no object"
+        )));
+    }
+
+    // Nobody can create or update objects on another server.
+    if matches!(activity_type, ActivityType::Create | ActivityType::Update) {
+        let embedded = activity
+            .object
+            .iter()
+            .flatten()
+            .filter_map(|object| object.as_object())
+            .filter_map(|object| object.id.as_deref());
+        for object_id in embedded {
+            if !same_origin(object_id, actor_id) {
+                return Err(Error::Validation(format!(
+                    "{actor_id} can't {activity_type} {object_id}"
+                )));
+            }
+        }
+    }
+    Ok(id)
+}
+
+///////////////////////////
+// Helpers
+///////////////////////////
+/// The document a key id refers to: `https://example.social/users/alice` for
+/// `https://example.social/users/alice#main-key`.
+fn document_id(key_id: &str) -> &str {
+    key_id.split('#').next().unwrap_or(key_id)
+}
+
+fn is_activity(content_type: &str) -> bool {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    media_type == "application/activity+json" || media_type == "application/ld+json"
+}
+
+fn respond(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    let mut response = Response::new(message.as_bytes().to_vec());
+    *response.status_mut() = status;
+    if !message.is_empty() {
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::MockFetcher;
+    use crate::keys::{KeyAlgorithm, KeyPair};
+    use crate::signature::tests::PRIVATE_KEY_PEM;
+    use crate::signature::{cavage, SigningKey};
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    const ALICE: &str = "https://example.social/users/alice";
+    const KEY_ID: &str = "https://example.social/users/alice#main-key";
+
+    /// Records the activities it's given, failing those of type `Fail`.
+    #[derive(Default)]
+    struct Recorder {
+        received: Mutex<Vec<String>>,
+    }
+
+    impl Recorder {
+        fn record(&self, method: &str, activity: &Activity) -> Result<()> {
+            let id = activity.base.id.clone().unwrap_or_default();
+            self.received.lock().unwrap().push(format!("{method} {id}"));
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl InboxHandler for Recorder {
+        async fn on_follow(&self, activity: &Activity, _actor: &Actor) -> Result<()> {
+            self.record("follow", activity)
+        }
+
+        async fn on_create(&self, activity: &Activity, _actor: &Actor) -> Result<()> {
+            self.record("create", activity)
+        }
+
+        async fn on_activity(&self, activity: &Activity, _actor: &Actor) -> Result<()> {
+            if activity.base.object_type.as_deref() == Some("Fail") {
+                return Err(Error::Validation("handler failed".into()));
+            }
+            self.record("other", activity)
+        }
+    }
+
+    fn alice() -> Value {
+        let key = KeyPair::from_pem(PRIVATE_KEY_PEM)
+            .unwrap()
+            .public_key_info(KEY_ID.into(), ALICE.into())
+            .unwrap();
+        json!({
+          "@context": ["https://www.w3.org/ns/activitystreams", "https://w3id.org/security/v1"],
+          "id": ALICE,
+          "type": "Person",
+          "inbox": "https://example.social/users/alice/inbox",
+          "publicKey": key
+        })
+    }
+
+    fn inbox() -> Inbox<Recorder, MockFetcher> {
+        let fetcher = MockFetcher::new();
+        fetcher.insert(ALICE, &alice()).unwrap();
+        Inbox::new(Recorder::default(), fetcher)
+    }
+
+    fn follow(id: &str) -> Value {
+        json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": id,
+          "type": "Follow",
+          "actor": ALICE,
+          "object": "https://local.example/users/bob"
+        })
+    }
+
+    fn delivery(activity: &Value) -> Request<Vec<u8>> {
+        let mut request = Request::post("https://local.example/users/bob/inbox")
+            .header(header::CONTENT_TYPE, "application/activity+json")
+            .body(serde_json::to_vec(activity).unwrap())
+            .unwrap();
+        let key = SigningKey::from_pem(KEY_ID.into(), PRIVATE_KEY_PEM).unwrap();
+        cavage::sign(&mut request, &key).unwrap();
+        request
+    }
+
+    fn received(inbox: &Inbox<Recorder, MockFetcher>) -> Vec<String> {
+        inbox.handler.received.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn handle_delivery() {
+        let inbox = inbox();
+        let activity = follow("https://example.social/activities/1");
+        let response = inbox.handle(delivery(&activity)).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        // Duplicates are accepted, but not handled again.
+        let response = inbox.handle(delivery(&activity)).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(
+            received(&inbox),
+            vec!["follow https://example.social/activities/1"]
+        );
+
+        // The actor was fetched once, for its key.
+        inbox
+            .handle(delivery(&follow("https://example.social/activities/2")))
+            .await;
+        assert_eq!(inbox.actors.fetcher.requests(), vec![ALICE]);
+    }
+
+    #[tokio::test]
+    async fn rejected_deliveries() {
+        let inbox = inbox();
+        let activity = follow("https://example.social/activities/1");
+
+        let mut request = delivery(&activity);
+        *request.method_mut() = Method::PUT;
+        assert_eq!(
+            inbox.handle(request).await.status(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+
+        let mut request = delivery(&activity);
+        request.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("text/html"),
+        );
+        assert_eq!(
+            inbox.handle(request).await.status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let mut request = delivery(&activity);
+        request.headers_mut().remove("signature");
+        assert_eq!(
+            inbox.handle(request).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let mut request = delivery(&activity);
+        *request.body_mut() =
+            serde_json::to_vec(&follow("https://example.social/activities/2")).unwrap();
+        assert_eq!(
+            inbox.handle(request).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // Signed by alice, claiming to be by bob.
+        let mut forged = activity.clone();
+        forged["actor"] = json!("https://example.social/users/bob");
+        assert_eq!(
+            inbox.handle(delivery(&forged)).await.status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let mut malformed = activity.clone();
+        malformed.as_object_mut().unwrap().remove("object");
+        assert_eq!(
+            inbox.handle(delivery(&malformed)).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert!(received(&inbox).is_empty());
+    }
+
+    #[tokio::test]
+    async fn rotated_key() {
+        let mut inbox = inbox();
+        let mut stale = alice();
+        stale["publicKey"]["publicKeyPem"] = json!(KeyPair::generate(KeyAlgorithm::Rsa2048)
+            .unwrap()
+            .public_key()
+            .to_pem()
+            .unwrap());
+        inbox.actors.fetcher.insert(ALICE, &stale).unwrap();
+        inbox.actors.actor(ALICE).await.unwrap();
+        inbox.actors.fetcher.insert(ALICE, &alice()).unwrap();
+
+        // Just fetched: the key isn't fetched again yet.
+        let response = inbox
+            .handle(delivery(&follow("https://example.social/activities/1")))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(inbox.actors.fetcher.requests(), vec![ALICE]);
+
+        inbox.min_refetch_age = Duration::ZERO;
+        let response = inbox
+            .handle(delivery(&follow("https://example.social/activities/1")))
+            .await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(inbox.actors.fetcher.requests(), vec![ALICE, ALICE]);
+    }
+
+    #[tokio::test]
+    async fn assertion_method_keys() {
+        let inbox = inbox();
+        let multikey = KeyPair::from_pem(PRIVATE_KEY_PEM)
+            .unwrap()
+            .multikey(KEY_ID.into(), ALICE.into())
+            .unwrap();
+        let mut alice = alice();
+        alice.as_object_mut().unwrap().remove("publicKey");
+        alice["assertionMethod"] = json!([multikey]);
+        inbox.actors.fetcher.insert(ALICE, &alice).unwrap();
+
+        let response = inbox
+            .handle(delivery(&follow("https://example.social/activities/1")))
+            .await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        // Keys controlled by someone else don't count.
+        let inbox = self::inbox();
+        alice["assertionMethod"][0]["controller"] = json!("https://example.social/users/mallory");
+        inbox.actors.fetcher.insert(ALICE, &alice).unwrap();
+        let response = inbox
+            .handle(delivery(&follow("https://example.social/activities/1")))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn bad_signatures_refetch_keys_once() {
+        let mut inbox = inbox();
+        inbox.actors.actor(ALICE).await.unwrap();
+        // Signed for another body.
+        let forged = || {
+            let mut request = delivery(&follow("https://example.social/activities/1"));
+            *request.body_mut() =
+                serde_json::to_vec(&follow("https://example.social/activities/2")).unwrap();
+            request
+        };
+
+        for _ in 0..3 {
+            assert_eq!(
+                inbox.handle(forged()).await.status(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+        assert_eq!(inbox.actors.fetcher.requests(), vec![ALICE]);
+
+        // Once the actor is old enough, it's fetched again, and cached anew.
+        inbox.min_refetch_age = Duration::ZERO;
+        inbox.handle(forged()).await;
+        assert_eq!(inbox.actors.fetcher.requests(), vec![ALICE, ALICE]);
+        inbox.min_refetch_age = Duration::from_secs(60);
+        for _ in 0..3 {
+            inbox.handle(forged()).await;
+        }
+        assert_eq!(inbox.actors.fetcher.requests(), vec![ALICE, ALICE]);
+    }
+
+    #[tokio::test]
+    async fn receive_validates() {
+        let inbox = inbox();
+        let actor: Document<Actor> = serde_json::from_value(alice()).unwrap();
+        let receive = |activity: Value| {
+            let document: Document<Activity> = serde_json::from_value(activity).unwrap();
+            inbox.receive(document, &actor.object)
+        };
+
+        let mut anonymous = follow("https://example.social/activities/1");
+        anonymous.as_object_mut().unwrap().remove("id");
+        assert!(matches!(
+            receive(anonymous).await,
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            receive(follow("https://elsewhere.example/activities/1")).await,
+            Err(Error::Validation(_))
+        ));
+
+        let create = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": "https://example.social/activities/2",
+          "type": "Create",
+          "actor": ALICE,
+          "object": { "id": "https://elsewhere.example/notes/1", "type": "Note" }
+        });
+        assert!(matches!(
+            receive(create.clone()).await,
+            Err(Error::Validation(_))
+        ));
+
+        let mut create = create;
+        create["object"]["id"] = json!("https://example.social/notes/1");
+        assert_eq!(receive(create).await.unwrap(), Received::Handled);
+
+        let question = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": "https://example.social/activities/3",
+          "type": "Question",
+          "actor": ALICE,
+          "name": "What's for dinner?"
+        });
+        assert_eq!(receive(question).await.unwrap(), Received::Handled);
+        assert_eq!(
+            received(&inbox),
+            vec![
+                "create https://example.social/activities/2",
+                "other https://example.social/activities/3"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_activities_can_be_retried() {
+        let inbox = inbox();
+        let actor: Document<Actor> = serde_json::from_value(alice()).unwrap();
+        let mut activity = follow("https://example.social/activities/1");
+        activity["type"] = json!("Fail");
+        let document: Document<Activity> = serde_json::from_value(activity).unwrap();
+
+        assert!(inbox
+            .receive(document.clone(), &actor.object)
+            .await
+            .is_err());
+        assert!(inbox.receive(document, &actor.object).await.is_err());
+
+        let mut inbox = inbox;
+        inbox.max_seen = 1;
+        for i in 0..3 {
+            let activity = follow(&format!("https://example.social/activities/{i}"));
+            let document: Document<Activity> = serde_json::from_value(activity).unwrap();
+            inbox.receive(document, &actor.object).await.unwrap();
+        }
+        assert_eq!(inbox.lock_seen().ids.len(), 1);
+    }
+}
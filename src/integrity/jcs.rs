@@ -0,0 +1,144 @@
+//! The JSON Canonicalization Scheme of
+//! [RFC 8785](https://www.rfc-editor.org/rfc/rfc8785): a serialization of
+//! JSON in which equal documents are equal byte for byte, so that they can be
+//! hashed and signed.
+
+use serde_json::Value;
+
+/// Serializes `value` canonically: no whitespace, object members sorted by
+/// the UTF-16 code units of their names, and numbers formatted as ECMAScript
+/// does.
+pub fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(true) => out.push_str("true"),
+        Value::Bool(false) => out.push_str("false"),
+        Value::Number(number) => out.push_str(&format_number(number.as_f64().unwrap_or(0.0))),
+        Value::String(string) => write_string(out, string),
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, value);
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            let mut members: Vec<_> = members.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (name, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, name);
+                out.push(':');
+                write_value(out, value);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// serde_json escapes exactly what RFC 8785 requires: `"`, `\` and control
+/// characters, the latter with lowercase hexadecimal digits.
+fn write_string(out: &mut String, string: &str) {
+    out.push_str(&Value::String(string.to_string()).to_string());
+}
+
+/// Formats a number like ECMAScript's `Number.prototype.toString`.
+fn format_number(number: f64) -> String {
+    if number == 0.0 {
+        // Including -0.
+        return "0".into();
+    }
+    // The shortest digits that round-trip, e.g. "1.2345e-7".
+    let scientific = format!("{:e}", number.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("exponent in scientific notation");
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().expect("integer exponent");
+
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+    let formatted = if k <= n && n <= 21 {
+        format!("{digits}{}", "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{digits}", "0".repeat(-n as usize))
+    } else {
+        let mantissa = match k {
+            1 => digits.clone(),
+            _ => format!("{}.{}", &digits[..1], &digits[1..]),
+        };
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        format!("{mantissa}e{sign}{}", (n - 1).abs())
+    };
+    if number < 0.0 {
+        format!("-{formatted}")
+    } else {
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    // The example of RFC 8785, section 3.2.2.
+    #[test]
+    fn rfc8785_example() {
+        let value: Value = serde_json::from_str(
+            r#"{
+              "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+              "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+              "literals": [null, true, false]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            canonicalize(&value),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn sort_by_utf16() {
+        // U+1F600 sorts before U+FB33 in UTF-16, after it in UTF-8.
+        let value = json!({ "\u{fb33}": 1, "\u{1f600}": 2, "a": { "c": 3, "b": [] } });
+        assert_eq!(
+            canonicalize(&value),
+            "{\"a\":{\"b\":[],\"c\":3},\"\u{1f600}\":2,\"\u{fb33}\":1}"
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        for (number, expected) in [
+            (1.0, "1"),
+            (-0.0, "0"),
+            (-1.5, "-1.5"),
+            (1e21, "1e+21"),
+            (1e20, "100000000000000000000"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (123456789.0, "123456789"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+            (5e-324, "5e-324"),
+        ] {
+            assert_eq!(format_number(number), expected);
+        }
+    }
+}
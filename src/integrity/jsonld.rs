@@ -0,0 +1,1027 @@
+//! Just enough [JSON-LD](https://www.w3.org/TR/json-ld11-api/) to turn an
+//! ActivityStreams document into RDF, so that it can be canonicalized with
+//! [urdna2015](super::urdna2015).
+//!
+//! Contexts are never fetched: a document may only refer to the
+//! [bundled](bundled_context) contexts, and embed any others. Features that
+//! are rare in the fediverse, e.g. scoped contexts and reverse properties, are
+//! rejected rather than ignored, as ignoring them would leave parts of a
+//! signed document out of its signature.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use crate::{Error, Result};
+
+pub const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+pub const RDF_FIRST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+pub const RDF_REST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+pub const RDF_NIL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+pub const RDF_LANG_STRING: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#langString";
+pub const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+pub const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+pub const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+pub const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+
+///////////////////////////
+// Bundled contexts
+///////////////////////////
+/// The contexts of ActivityStreams and of the W3C security vocabulary. The
+/// identity context is abridged to the terms of signature options.
+const BUNDLED_CONTEXTS: &[(&str, &str)] = &[
+    (
+        "https://www.w3.org/ns/activitystreams",
+        include_str!("contexts/activitystreams.jsonld"),
+    ),
+    (
+        "https://w3id.org/security/v1",
+        include_str!("contexts/security-v1.jsonld"),
+    ),
+    (
+        "https://w3id.org/identity/v1",
+        include_str!("contexts/identity-v1.jsonld"),
+    ),
+];
+
+/// The bundled copy of the context document at `url`, if there is one.
+pub fn bundled_context(url: &str) -> Option<&'static str> {
+    let url = url.strip_suffix(".jsonld").unwrap_or(url);
+    let url = url.strip_suffix('#').unwrap_or(url);
+    let url = match url.strip_prefix("http://") {
+        Some(rest) => format!("https://{rest}"),
+        None => url.to_string(),
+    };
+    BUNDLED_CONTEXTS
+        .iter()
+        .find(|(bundled, _)| *bundled == url)
+        .map(|(_, document)| *document)
+}
+
+///////////////////////////
+// RDF
+///////////////////////////
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Term {
+    Iri(String),
+    BlankNode(String),
+    Literal {
+        value: String,
+        datatype: String,
+        language: Option<String>,
+    },
+}
+
+/// A triple, in the default graph or a named one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Quad {
+    pub subject: Term,
+    pub predicate: Term,
+    pub object: Term,
+    pub graph: Option<Term>,
+}
+
+/// The RDF dataset of a JSON-LD document, without duplicate quads. Blank nodes
+/// are labelled `_:b0`, `_:b1` and so on.
+pub fn to_rdf(document: &Value) -> Result<Vec<Quad>> {
+    let expanded = expand(document)?;
+    let mut converter = Converter::default();
+    for node in &expanded {
+        if let Value::Object(node) = node {
+            converter.node(node, None)?;
+        }
+    }
+    let mut quads = converter.quads;
+    quads.sort();
+    quads.dedup();
+    Ok(quads)
+}
+
+/// The expanded form of a JSON-LD document: an array of node objects with
+/// absolute IRIs for properties and no `@context`.
+pub fn expand(document: &Value) -> Result<Vec<Value>> {
+    let expanded = expand_element(&Context::default(), None, document)?;
+    let expanded = match expanded {
+        Value::Object(mut node) if node.len() == 1 && node.contains_key("@graph") => {
+            node.remove("@graph").unwrap_or(Value::Null)
+        }
+        expanded => expanded,
+    };
+    Ok(match expanded {
+        Value::Null => Vec::new(),
+        Value::Array(nodes) => nodes,
+        node => vec![node],
+    })
+}
+
+///////////////////////////
+// Context processing
+///////////////////////////
+#[derive(Debug, Clone, Default)]
+struct Context {
+    /// Terms mapped to `None` are explicitly ignored.
+    terms: HashMap<String, Option<TermDefinition>>,
+    vocab: Option<String>,
+    language: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TermDefinition {
+    iri: String,
+    type_mapping: Option<String>,
+    containers: Vec<String>,
+    /// `Some(None)` if the term explicitly has no language.
+    language: Option<Option<String>>,
+}
+
+impl TermDefinition {
+    fn has_container(&self, container: &str) -> bool {
+        self.containers.iter().any(|c| c == container)
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "@base",
+    "@container",
+    "@context",
+    "@direction",
+    "@graph",
+    "@id",
+    "@import",
+    "@included",
+    "@index",
+    "@json",
+    "@language",
+    "@list",
+    "@nest",
+    "@none",
+    "@prefix",
+    "@propagate",
+    "@protected",
+    "@reverse",
+    "@set",
+    "@type",
+    "@value",
+    "@version",
+    "@vocab",
+];
+
+fn is_keyword(value: &str) -> bool {
+    KEYWORDS.contains(&value)
+}
+
+fn unsupported(feature: &str) -> Error {
+    Error::Validation(format!("unsupported JSON-LD feature: {feature}"))
+}
+
+fn invalid(message: String) -> Error {
+    Error::Validation(format!("invalid JSON-LD: {message}"))
+}
+
+fn process_context(active: &Context, local: &Value, remote: &mut Vec<String>) -> Result<Context> {
+    let mut result = active.clone();
+    let contexts = match local {
+        Value::Array(contexts) => contexts.iter().collect(),
+        context => vec![context],
+    };
+    for context in contexts {
+        match context {
+            Value::Null => result = Context::default(),
+            Value::String(url) => {
+                if remote.contains(url) {
+                    return Err(invalid(format!("recursive context inclusion of {url}")));
+                }
+                let document = bundled_context(url)
+                    .ok_or_else(|| Error::Validation(format!("unknown JSON-LD context {url}")))?;
+                let document: Value = serde_json::from_str(document)?;
+                let inner = document
+                    .get("@context")
+                    .ok_or_else(|| invalid(format!("{url} has no @context")))?;
+                remote.push(url.clone());
+                result = process_context(&result, inner, remote)?;
+                remote.pop();
+            }
+            Value::Object(definitions) => {
+                for keyword in ["@import", "@propagate"] {
+                    if definitions.contains_key(keyword) {
+                        return Err(unsupported(keyword));
+                    }
+                }
+                if !matches!(definitions.get("@base"), None | Some(Value::Null)) {
+                    return Err(unsupported("@base"));
+                }
+                match definitions.get("@vocab") {
+                    None => {}
+                    Some(Value::Null) => result.vocab = None,
+                    Some(Value::String(vocab)) => result.vocab = expand_iri(&result, vocab, true),
+                    Some(vocab) => return Err(invalid(format!("invalid @vocab {vocab}"))),
+                }
+                match definitions.get("@language") {
+                    None => {}
+                    Some(Value::Null) => result.language = None,
+                    Some(Value::String(language)) => result.language = Some(language.clone()),
+                    Some(language) => return Err(invalid(format!("invalid @language {language}"))),
+                }
+
+                let mut defined = HashMap::new();
+                for term in definitions.keys() {
+                    if !matches!(
+                        term.as_str(),
+                        "@base" | "@language" | "@protected" | "@version" | "@vocab"
+                    ) {
+                        define_term(&mut result, definitions, term, &mut defined)?;
+                    }
+                }
+            }
+            context => return Err(invalid(format!("invalid @context {context}"))),
+        }
+    }
+    Ok(result)
+}
+
+/// Creates the definition of `term` in `local`, first defining the terms it
+/// depends on. `defined` tracks the terms being defined, to detect cycles.
+fn define_term(
+    active: &mut Context,
+    local: &Map<String, Value>,
+    term: &str,
+    defined: &mut HashMap<String, bool>,
+) -> Result<()> {
+    match defined.get(term) {
+        Some(true) => return Ok(()),
+        Some(false) => return Err(invalid(format!("cyclic definition of {term}"))),
+        None => {}
+    }
+    defined.insert(term.to_string(), false);
+    if is_keyword(term) {
+        return Err(invalid(format!("keyword {term} redefined")));
+    }
+
+    let definition = match &local[term] {
+        Value::Null => json!({ "@id": null }),
+        Value::String(iri) => json!({ "@id": iri }),
+        definition @ Value::Object(_) => definition.clone(),
+        definition => {
+            return Err(invalid(format!(
+                "invalid definition of {term}: {definition}"
+            )))
+        }
+    };
+    for keyword in ["@context", "@index", "@nest", "@reverse"] {
+        if definition.get(keyword).is_some() {
+            return Err(unsupported(keyword));
+        }
+    }
+
+    let iri = match definition.get("@id") {
+        Some(Value::Null) => None,
+        Some(Value::String(id)) => context_iri(active, local, defined, id)?,
+        Some(id) => return Err(invalid(format!("invalid @id {id} of {term}"))),
+        None => match term.split_once(':') {
+            Some((prefix, suffix)) => {
+                if local.contains_key(prefix) {
+                    define_term(active, local, prefix, defined)?;
+                }
+                match active.terms.get(prefix) {
+                    Some(Some(prefix)) => Some(format!("{}{suffix}", prefix.iri)),
+                    _ => Some(term.to_string()),
+                }
+            }
+            None => match &active.vocab {
+                Some(vocab) => Some(format!("{vocab}{term}")),
+                None => return Err(invalid(format!("{term} has no IRI"))),
+            },
+        },
+    };
+    let Some(iri) = iri else {
+        active.terms.insert(term.to_string(), None);
+        defined.insert(term.to_string(), true);
+        return Ok(());
+    };
+
+    let type_mapping = match definition.get("@type") {
+        None => None,
+        Some(Value::String(type_mapping)) => {
+            match context_iri(active, local, defined, type_mapping)? {
+                Some(iri) if iri == "@json" => return Err(unsupported("@json")),
+                Some(iri) if iri == "@id" || iri == "@vocab" || iri == "@none" => Some(iri),
+                Some(iri) if is_absolute_iri(&iri) => Some(iri),
+                _ => return Err(invalid(format!("invalid @type of {term}"))),
+            }
+        }
+        Some(type_mapping) => return Err(invalid(format!("invalid @type {type_mapping}"))),
+    };
+
+    let containers = match definition.get("@container") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::String(container)) => vec![container.clone()],
+        Some(Value::Array(containers)) => containers
+            .iter()
+            .map(|container| container.as_str().map(str::to_string))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid(format!("invalid @container of {term}")))?,
+        Some(container) => return Err(invalid(format!("invalid @container {container}"))),
+    };
+    for container in &containers {
+        match container.as_str() {
+            "@index" | "@language" | "@list" | "@set" => {}
+            "@graph" | "@id" | "@type" => return Err(unsupported(container)),
+            _ => return Err(invalid(format!("invalid @container {container}"))),
+        }
+    }
+
+    let language = match definition.get("@language") {
+        None => None,
+        Some(Value::Null) => Some(None),
+        Some(Value::String(language)) => Some(Some(language.clone())),
+        Some(language) => return Err(invalid(format!("invalid @language {language}"))),
+    };
+
+    active.terms.insert(
+        term.to_string(),
+        Some(TermDefinition {
+            iri,
+            type_mapping,
+            containers,
+            language,
+        }),
+    );
+    defined.insert(term.to_string(), true);
+    Ok(())
+}
+
+/// Expands an IRI in a context that is being processed, defining the terms of
+/// the context it uses first.
+fn context_iri(
+    active: &mut Context,
+    local: &Map<String, Value>,
+    defined: &mut HashMap<String, bool>,
+    value: &str,
+) -> Result<Option<String>> {
+    if is_keyword(value) {
+        return Ok(Some(value.to_string()));
+    }
+    if local.contains_key(value) && defined.get(value) != Some(&true) {
+        define_term(active, local, value, defined)?;
+    }
+    if let Some((prefix, _)) = value.split_once(':') {
+        if local.contains_key(prefix) && defined.get(prefix) != Some(&true) {
+            define_term(active, local, prefix, defined)?;
+        }
+    }
+    Ok(expand_iri(active, value, true))
+}
+
+/// Expands a keyword alias, term, compact IRI or, if `vocab`, a vocabulary
+/// relative IRI. `None` if the value maps to nothing.
+fn expand_iri(active: &Context, value: &str, vocab: bool) -> Option<String> {
+    if is_keyword(value) {
+        return Some(value.to_string());
+    }
+    if value.len() > 1
+        && value.starts_with('@')
+        && value[1..].chars().all(|c| c.is_ascii_alphabetic())
+    {
+        return None;
+    }
+    if vocab {
+        if let Some(definition) = active.terms.get(value) {
+            return definition.as_ref().map(|definition| definition.iri.clone());
+        }
+    }
+    if let Some((prefix, suffix)) = value.split_once(':') {
+        if prefix == "_" || suffix.starts_with("//") {
+            return Some(value.to_string());
+        }
+        if let Some(Some(definition)) = active.terms.get(prefix) {
+            return Some(format!("{}{suffix}", definition.iri));
+        }
+        return Some(value.to_string());
+    }
+    match &active.vocab {
+        Some(prefix) if vocab => Some(format!("{prefix}{value}")),
+        // Relative to a base IRI, which documents from the network don't have.
+        _ => Some(value.to_string()),
+    }
+}
+
+fn is_absolute_iri(value: &str) -> bool {
+    match value.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+///////////////////////////
+// Expansion
+///////////////////////////
+fn expand_element(active: &Context, property: Option<&str>, element: &Value) -> Result<Value> {
+    let definition = property.and_then(|property| active.terms.get(property).cloned().flatten());
+    match element {
+        Value::Null => Ok(Value::Null),
+        Value::Array(items) => {
+            let mut result = Vec::new();
+            for item in items {
+                let expanded = expand_element(active, property, item)?;
+                match expanded {
+                    Value::Array(expanded)
+                        if definition
+                            .as_ref()
+                            .is_some_and(|d| d.has_container("@list")) =>
+                    {
+                        result.push(json!({ "@list": expanded }))
+                    }
+                    Value::Array(expanded) => result.extend(expanded),
+                    Value::Null => {}
+                    expanded => result.push(expanded),
+                }
+            }
+            Ok(Value::Array(result))
+        }
+        Value::Object(element) => expand_object(active, property, element),
+        scalar => match property {
+            None | Some("@graph") => Ok(Value::Null),
+            Some(property) => Ok(expand_value(active, property, scalar)),
+        },
+    }
+}
+
+fn expand_object(
+    active: &Context,
+    property: Option<&str>,
+    element: &Map<String, Value>,
+) -> Result<Value> {
+    let context;
+    let active = match element.get("@context") {
+        Some(local) => {
+            context = process_context(active, local, &mut Vec::new())?;
+            &context
+        }
+        None => active,
+    };
+
+    let mut keys: Vec<_> = element.keys().collect();
+    keys.sort();
+    let mut result = Map::new();
+    for key in keys {
+        let value = &element[key];
+        if key == "@context" {
+            continue;
+        }
+        let Some(expanded_property) = expand_iri(active, key, true) else {
+            continue;
+        };
+        if !expanded_property.contains(':') && !is_keyword(&expanded_property) {
+            continue;
+        }
+
+        if is_keyword(&expanded_property) {
+            if result.contains_key(&expanded_property) {
+                return Err(invalid(format!("colliding keywords {expanded_property}")));
+            }
+            let expanded = match expanded_property.as_str() {
+                "@id" => match value {
+                    Value::String(id) => expand_iri(active, id, false)
+                        .map(Value::String)
+                        .unwrap_or(Value::Null),
+                    id => return Err(invalid(format!("invalid @id {id}"))),
+                },
+                "@type" => {
+                    let expand_type = |value: &Value| match value {
+                        Value::String(value) => expand_iri(active, value, true)
+                            .map(Value::String)
+                            .ok_or_else(|| invalid(format!("invalid @type {value}"))),
+                        value => Err(invalid(format!("invalid @type {value}"))),
+                    };
+                    match value {
+                        Value::Array(types) => {
+                            Value::Array(types.iter().map(expand_type).collect::<Result<_>>()?)
+                        }
+                        value => expand_type(value)?,
+                    }
+                }
+                "@graph" => as_array(expand_element(active, Some("@graph"), value)?),
+                "@value" => match value {
+                    Value::Array(_) | Value::Object(_) => {
+                        return Err(invalid(format!("invalid @value {value}")))
+                    }
+                    value => value.clone(),
+                },
+                "@language" => match value {
+                    Value::String(_) => value.clone(),
+                    value => return Err(invalid(format!("invalid @language {value}"))),
+                },
+                "@index" => match value {
+                    Value::String(_) => value.clone(),
+                    value => return Err(invalid(format!("invalid @index {value}"))),
+                },
+                "@list" => {
+                    if matches!(property, None | Some("@graph")) {
+                        continue;
+                    }
+                    as_array(expand_element(active, property, value)?)
+                }
+                "@set" => expand_element(active, property, value)?,
+                "@direction" | "@included" | "@nest" | "@reverse" => {
+                    return Err(unsupported(&expanded_property))
+                }
+                _ => continue,
+            };
+            result.insert(expanded_property, expanded);
+            continue;
+        }
+
+        let definition = active.terms.get(key.as_str()).cloned().flatten();
+        let has_container = |container| {
+            definition
+                .as_ref()
+                .is_some_and(|d| d.has_container(container))
+        };
+        let expanded = match value {
+            Value::Object(map) if has_container("@language") => expand_language_map(active, map)?,
+            Value::Object(map) if has_container("@index") => expand_index_map(active, key, map)?,
+            value => expand_element(active, Some(key), value)?,
+        };
+        if expanded.is_null() {
+            continue;
+        }
+        let expanded = if has_container("@list") && !is_list(&expanded) {
+            json!({ "@list": as_array(expanded) })
+        } else {
+            expanded
+        };
+        let values = result
+            .entry(expanded_property)
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(values) = values {
+            match expanded {
+                Value::Array(expanded) => values.extend(expanded),
+                expanded => values.push(expanded),
+            }
+        }
+    }
+
+    if let Some(value) = result.get("@value") {
+        if let Some(key) = result
+            .keys()
+            .find(|key| !matches!(key.as_str(), "@value" | "@language" | "@type" | "@index"))
+        {
+            return Err(invalid(format!("value object with {key}")));
+        }
+        if value.is_null() {
+            return Ok(Value::Null);
+        }
+        if result.contains_key("@language") && !value.is_string() {
+            return Err(invalid(format!("language-tagged {value}")));
+        }
+        if !matches!(result.get("@type"), None | Some(Value::String(_))) {
+            return Err(invalid("value object with several types".into()));
+        }
+    } else if let Some(types) = result.get_mut("@type") {
+        if !types.is_array() {
+            *types = Value::Array(vec![types.take()]);
+        }
+    } else if result.contains_key("@set") || result.contains_key("@list") {
+        if let Some(key) = result
+            .keys()
+            .find(|key| !matches!(key.as_str(), "@set" | "@list" | "@index"))
+        {
+            return Err(invalid(format!("list or set object with {key}")));
+        }
+        if let Some(set) = result.remove("@set") {
+            return Ok(set);
+        }
+    }
+
+    if result.len() == 1 && result.contains_key("@language") {
+        return Ok(Value::Null);
+    }
+    if matches!(property, None | Some("@graph"))
+        && (result.is_empty()
+            || result.contains_key("@value")
+            || result.contains_key("@list")
+            || (result.len() == 1 && result.contains_key("@id")))
+    {
+        return Ok(Value::Null);
+    }
+    Ok(Value::Object(result))
+}
+
+fn expand_value(active: &Context, property: &str, value: &Value) -> Value {
+    let definition = active.terms.get(property).cloned().flatten();
+    let type_mapping = definition.as_ref().and_then(|d| d.type_mapping.as_deref());
+    match (type_mapping, value) {
+        (Some("@id"), Value::String(id)) => match expand_iri(active, id, false) {
+            Some(id) => json!({ "@id": id }),
+            None => Value::Null,
+        },
+        (Some("@vocab"), Value::String(id)) => match expand_iri(active, id, true) {
+            Some(id) => json!({ "@id": id }),
+            None => Value::Null,
+        },
+        (Some(datatype), value) if !datatype.starts_with('@') => {
+            json!({ "@value": value, "@type": datatype })
+        }
+        (_, Value::String(_)) => {
+            let language = match definition.and_then(|d| d.language) {
+                Some(language) => language,
+                None => active.language.clone(),
+            };
+            match language {
+                Some(language) => json!({ "@value": value, "@language": language }),
+                None => json!({ "@value": value }),
+            }
+        }
+        (_, value) => json!({ "@value": value }),
+    }
+}
+
+fn expand_language_map(active: &Context, map: &Map<String, Value>) -> Result<Value> {
+    let mut languages: Vec<_> = map.iter().collect();
+    languages.sort_by_key(|(language, _)| *language);
+    let mut result = Vec::new();
+    for (language, values) in languages {
+        for value in into_vec(values.clone()) {
+            match value {
+                Value::Null => {}
+                Value::String(_)
+                    if expand_iri(active, language, true).as_deref() == Some("@none") =>
+                {
+                    result.push(json!({ "@value": value }))
+                }
+                Value::String(_) => result.push(json!({ "@value": value, "@language": language })),
+                value => return Err(invalid(format!("invalid language map value {value}"))),
+            }
+        }
+    }
+    Ok(Value::Array(result))
+}
+
+fn expand_index_map(active: &Context, property: &str, map: &Map<String, Value>) -> Result<Value> {
+    let mut indexes: Vec<_> = map.iter().collect();
+    indexes.sort_by_key(|(index, _)| *index);
+    let mut result = Vec::new();
+    for (index, values) in indexes {
+        for mut value in into_vec(expand_element(active, Some(property), values)?) {
+            if let Value::Object(item) = &mut value {
+                if index != "@none" && !item.contains_key("@index") {
+                    item.insert("@index".into(), Value::String(index.clone()));
+                }
+            }
+            result.push(value);
+        }
+    }
+    Ok(Value::Array(result))
+}
+
+fn as_array(value: Value) -> Value {
+    Value::Array(into_vec(value))
+}
+
+fn into_vec(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(values) => values,
+        Value::Null => Vec::new(),
+        value => vec![value],
+    }
+}
+
+fn is_list(value: &Value) -> bool {
+    value.get("@list").is_some()
+}
+
+///////////////////////////
+// Conversion to RDF
+///////////////////////////
+#[derive(Default)]
+struct Converter {
+    quads: Vec<Quad>,
+    /// Blank nodes of the document, relabelled so that they can't collide
+    /// with generated ones.
+    labels: HashMap<String, String>,
+    counter: usize,
+}
+
+impl Converter {
+    /// Adds the quads of a node object, returning its subject unless it has a
+    /// relative IRI.
+    fn node(&mut self, node: &Map<String, Value>, graph: Option<&Term>) -> Result<Option<Term>> {
+        let subject = match node.get("@id") {
+            Some(Value::String(id)) => self.resource(id),
+            _ => Some(self.blank_node()),
+        };
+
+        if let Some(Value::Array(types)) = node.get("@type") {
+            for node_type in types {
+                if let Some(node_type) = node_type.as_str().and_then(|t| self.resource(t)) {
+                    self.push(&subject, RDF_TYPE, Some(node_type), graph);
+                }
+            }
+        }
+
+        let mut properties: Vec<_> = node.iter().filter(|(p, _)| !is_keyword(p)).collect();
+        properties.sort_by_key(|(property, _)| *property);
+        for (property, values) in properties {
+            let Value::Array(values) = values else {
+                return Err(invalid(format!("invalid values of {property}")));
+            };
+            for value in values {
+                let object = self.object(value, graph)?;
+                // Blank node properties only exist in generalized RDF.
+                if is_absolute_iri(property) && !property.starts_with("_:") {
+                    self.push(&subject, property, object, graph);
+                }
+            }
+        }
+
+        if let Some(Value::Array(nodes)) = node.get("@graph") {
+            if let Some(name) = &subject {
+                for inner in nodes {
+                    if let Value::Object(inner) = inner {
+                        self.node(inner, Some(name))?;
+                    }
+                }
+            }
+        }
+        Ok(subject)
+    }
+
+    fn object(&mut self, item: &Value, graph: Option<&Term>) -> Result<Option<Term>> {
+        let Value::Object(item) = item else {
+            return Err(invalid(format!("invalid expanded value {item}")));
+        };
+        if item.contains_key("@value") {
+            literal(item).map(Some)
+        } else if let Some(list) = item.get("@list") {
+            let Value::Array(list) = list else {
+                return Err(invalid(format!("invalid @list {list}")));
+            };
+            self.list(list, graph)
+        } else {
+            self.node(item, graph)
+        }
+    }
+
+    fn list(&mut self, items: &[Value], graph: Option<&Term>) -> Result<Option<Term>> {
+        let objects = items
+            .iter()
+            .map(|item| self.object(item, graph))
+            .collect::<Result<Vec<_>>>()?;
+        let heads: Vec<_> = objects.iter().map(|_| Some(self.blank_node())).collect();
+        for (i, object) in objects.into_iter().enumerate() {
+            self.push(&heads[i], RDF_FIRST, object, graph);
+            let rest = heads
+                .get(i + 1)
+                .cloned()
+                .unwrap_or_else(|| Some(Term::Iri(RDF_NIL.into())));
+            self.push(&heads[i], RDF_REST, rest, graph);
+        }
+        Ok(heads
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| Some(Term::Iri(RDF_NIL.into()))))
+    }
+
+    fn resource(&mut self, id: &str) -> Option<Term> {
+        if id.starts_with("_:") {
+            let label = match self.labels.get(id) {
+                Some(label) => label.clone(),
+                None => {
+                    let label = self.next_label();
+                    self.labels.insert(id.to_string(), label.clone());
+                    label
+                }
+            };
+            Some(Term::BlankNode(label))
+        } else if is_absolute_iri(id) {
+            Some(Term::Iri(id.to_string()))
+        } else {
+            None
+        }
+    }
+
+    fn blank_node(&mut self) -> Term {
+        Term::BlankNode(self.next_label())
+    }
+
+    fn next_label(&mut self) -> String {
+        let label = format!("_:b{}", self.counter);
+        self.counter += 1;
+        label
+    }
+
+    fn push(
+        &mut self,
+        subject: &Option<Term>,
+        predicate: &str,
+        object: Option<Term>,
+        graph: Option<&Term>,
+    ) {
+        if let (Some(subject), Some(object)) = (subject, object) {
+            self.quads.push(Quad {
+                subject: subject.clone(),
+                predicate: Term::Iri(predicate.to_string()),
+                object,
+                graph: graph.cloned(),
+            });
+        }
+    }
+}
+
+fn literal(item: &Map<String, Value>) -> Result<Term> {
+    let datatype = item.get("@type").and_then(Value::as_str);
+    let language = item.get("@language").and_then(Value::as_str);
+    let (value, datatype) = match &item["@value"] {
+        Value::Bool(value) => (value.to_string(), datatype.unwrap_or(XSD_BOOLEAN)),
+        Value::Number(number) if datatype != Some(XSD_DOUBLE) && !number.is_f64() => {
+            (number.to_string(), datatype.unwrap_or(XSD_INTEGER))
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if number.fract() == 0.0 && number.abs() < 1e21 && datatype != Some(XSD_DOUBLE) {
+                (format!("{number:.0}"), datatype.unwrap_or(XSD_INTEGER))
+            } else {
+                (canonical_double(number), datatype.unwrap_or(XSD_DOUBLE))
+            }
+        }
+        Value::String(value) => match language {
+            Some(_) => (value.clone(), RDF_LANG_STRING),
+            None => (value.clone(), datatype.unwrap_or(XSD_STRING)),
+        },
+        value => return Err(invalid(format!("invalid @value {value}"))),
+    };
+    Ok(Term::Literal {
+        value,
+        datatype: datatype.to_string(),
+        language: language.map(str::to_string),
+    })
+}
+
+/// The canonical lexical form of an `xsd:double`, e.g. `1.5E1`.
+fn canonical_double(number: f64) -> String {
+    let formatted = format!("{number:.15E}");
+    let (mantissa, exponent) = formatted
+        .split_once('E')
+        .expect("exponent in scientific notation");
+    let mantissa = mantissa.trim_end_matches('0');
+    let mantissa = match mantissa.strip_suffix('.') {
+        Some(integer) => format!("{integer}.0"),
+        None => mantissa.to_string(),
+    };
+    format!("{mantissa}E{exponent}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn iri(iri: &str) -> Term {
+        Term::Iri(iri.into())
+    }
+
+    fn string(value: &str) -> Term {
+        Term::Literal {
+            value: value.into(),
+            datatype: XSD_STRING.into(),
+            language: None,
+        }
+    }
+
+    fn triple(subject: Term, predicate: &str, object: Term) -> Quad {
+        Quad {
+            subject,
+            predicate: iri(predicate),
+            object,
+            graph: None,
+        }
+    }
+
+    #[test]
+    fn activitystreams() {
+        let document = json!({
+          "@context": [
+            "https://www.w3.org/ns/activitystreams",
+            { "toot": "http://joinmastodon.org/ns#", "sensitive": "as:sensitive" }
+          ],
+          "id": "https://example.com/notes/1",
+          "type": "Note",
+          "attributedTo": "https://example.com/users/alice",
+          "content": "Hello",
+          "contentMap": { "en": "Hello" },
+          "sensitive": false,
+          "published": "2024-01-01T00:00:00Z",
+          "unknown": "dropped"
+        });
+        let note = iri("https://example.com/notes/1");
+        let mut expected = vec![
+            triple(
+                note.clone(),
+                "https://www.w3.org/ns/activitystreams#attributedTo",
+                iri("https://example.com/users/alice"),
+            ),
+            triple(
+                note.clone(),
+                "https://www.w3.org/ns/activitystreams#content",
+                string("Hello"),
+            ),
+            triple(
+                note.clone(),
+                "https://www.w3.org/ns/activitystreams#content",
+                Term::Literal {
+                    value: "Hello".into(),
+                    datatype: RDF_LANG_STRING.into(),
+                    language: Some("en".into()),
+                },
+            ),
+            triple(
+                note.clone(),
+                "https://www.w3.org/ns/activitystreams#published",
+                Term::Literal {
+                    value: "2024-01-01T00:00:00Z".into(),
+                    datatype: "http://www.w3.org/2001/XMLSchema#dateTime".into(),
+                    language: None,
+                },
+            ),
+            triple(
+                note.clone(),
+                "https://www.w3.org/ns/activitystreams#sensitive",
+                Term::Literal {
+                    value: "false".into(),
+                    datatype: XSD_BOOLEAN.into(),
+                    language: None,
+                },
+            ),
+            triple(
+                note,
+                RDF_TYPE,
+                iri("https://www.w3.org/ns/activitystreams#Note"),
+            ),
+        ];
+        expected.sort();
+        assert_eq!(to_rdf(&document).unwrap(), expected);
+    }
+
+    #[test]
+    fn embedded_nodes_and_lists() {
+        let document = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "type": "OrderedCollection",
+          "orderedItems": ["https://example.com/1", { "type": "Note", "content": "Hi" }]
+        });
+        let quads = to_rdf(&document).unwrap();
+        let count = |predicate: &str| {
+            quads
+                .iter()
+                .filter(|q| q.predicate == iri(predicate))
+                .count()
+        };
+        assert_eq!(count(RDF_FIRST), 2);
+        assert_eq!(count(RDF_REST), 2);
+        assert_eq!(count(RDF_TYPE), 2);
+        assert!(quads.iter().any(|q| q.object == iri(RDF_NIL)));
+        assert!(quads
+            .iter()
+            .any(|q| q.object == iri("https://example.com/1")));
+    }
+
+    #[test]
+    fn contexts() {
+        assert!(bundled_context("http://www.w3.org/ns/activitystreams").is_some());
+        assert!(bundled_context("https://w3id.org/security/v1").is_some());
+        assert!(bundled_context("https://example.com/context").is_none());
+
+        let remote = json!({ "@context": "https://example.com/context", "name": "a" });
+        assert!(matches!(to_rdf(&remote), Err(Error::Validation(_))));
+        let scoped =
+            json!({ "@context": { "a": { "@id": "https://example.com/a", "@context": {} } } });
+        assert!(matches!(to_rdf(&scoped), Err(Error::Validation(_))));
+        let cyclic = json!({ "@context": { "a": "b:x", "b": "a:y" }, "a": "value" });
+        assert!(matches!(to_rdf(&cyclic), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn numbers() {
+        for (number, expected) in [
+            (json!(1), ("1", XSD_INTEGER)),
+            (json!(2.0), ("2", XSD_INTEGER)),
+            (json!(1.5), ("1.5E0", XSD_DOUBLE)),
+            (json!(0.001), ("1.0E-3", XSD_DOUBLE)),
+            (json!(1e21), ("1.0E21", XSD_DOUBLE)),
+        ] {
+            let item = json!({ "@value": number });
+            let Term::Literal {
+                value, datatype, ..
+            } = literal(item.as_object().unwrap()).unwrap()
+            else {
+                panic!("not a literal");
+            };
+            assert_eq!((value.as_str(), datatype.as_str()), expected);
+        }
+    }
+}
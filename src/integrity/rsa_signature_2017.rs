@@ -0,0 +1,295 @@
+//! Linked Data Signatures of type `RsaSignature2017`, which Mastodon still
+//! attaches to the public activities it forwards.
+//!
+//! The signature is made over the [urdna2015] canonicalization of the
+//! document's RDF form, not over its JSON: only the terms that the document's
+//! contexts define are signed, and the document must only use
+//! [bundled](jsonld::bundled_context) or embedded contexts.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rsa::{
+    pkcs1v15,
+    signature::{SignatureEncoding, Signer, Verifier},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::{as_object_mut, jsonld, urdna2015};
+use crate::core::actor::PublicKeyInfo;
+use crate::core::{Document, LinkedDataSignature};
+use crate::signature::SigningKey;
+use crate::{Error, Result};
+
+/// The value of the signature's `type` property.
+pub const TYPE: &str = "RsaSignature2017";
+
+/// The context that the signature options are canonicalized with.
+const OPTIONS_CONTEXT: &str = "https://w3id.org/identity/v1";
+
+///////////////////////////
+// Signing
+///////////////////////////
+/// Adds a signature by `key` to a JSON document, replacing any signature it
+/// already has.
+pub fn sign(document: &mut Value, key: &SigningKey) -> Result<()> {
+    let signature = create_signature(document, key, Utc::now())?;
+    let signature = serde_json::to_value(signature)?;
+    as_object_mut(document)?.insert("signature".into(), signature);
+    Ok(())
+}
+
+/// Creates a signature over a JSON document, ignoring the signature it
+/// already has.
+pub fn create_signature(
+    document: &Value,
+    key: &SigningKey,
+    created: DateTime<Utc>,
+) -> Result<LinkedDataSignature> {
+    let mut signature = LinkedDataSignature {
+        signature_type: TYPE.into(),
+        creator: Some(key.key_id.clone()),
+        created: Some(created.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        signature_value: None,
+        extensions: Default::default(),
+    };
+    let data = signed_data(document, &serde_json::to_value(&signature)?)?;
+    let signer = pkcs1v15::SigningKey::<Sha256>::new(key.private_key.clone());
+    signature.signature_value = Some(BASE64.encode(signer.sign(data.as_bytes()).to_bytes()));
+    Ok(signature)
+}
+
+///////////////////////////
+// Verification
+///////////////////////////
+/// The id of the key a JSON document claims to be signed with, so that the
+/// key can be fetched before verifying the document.
+pub fn creator(document: &Value) -> Result<String> {
+    signature(document)?
+        .creator
+        .ok_or_else(|| Error::Signature("signature has no creator".into()))
+}
+
+/// Verifies the `RsaSignature2017` signature of a JSON document with `key`,
+/// which must be the key the signature names as its creator.
+pub fn verify(document: &Value, key: &PublicKeyInfo) -> Result<()> {
+    let signature = signature(document)?;
+    if signature.signature_type != TYPE {
+        return Err(Error::Signature(format!(
+            "unsupported signature type {}",
+            signature.signature_type
+        )));
+    }
+    let creator = signature
+        .creator
+        .ok_or_else(|| Error::Signature("signature has no creator".into()))?;
+    if creator != key.id {
+        return Err(Error::Signature(format!(
+            "document is signed by {creator}, not {}",
+            key.id
+        )));
+    }
+
+    let signature_value = signature
+        .signature_value
+        .ok_or_else(|| Error::Signature("signature has no signatureValue".into()))?;
+    let bytes = BASE64
+        .decode(signature_value)
+        .map_err(|_| Error::Signature("signatureValue is not valid base64".into()))?;
+    let bytes = pkcs1v15::Signature::try_from(bytes.as_slice())
+        .map_err(|_| Error::Signature("invalid signatureValue".into()))?;
+    let data = signed_data(document, &document["signature"])?;
+    pkcs1v15::VerifyingKey::<Sha256>::new(key.public_key()?)
+        .verify(data.as_bytes(), &bytes)
+        .map_err(|_| Error::Signature("signature doesn't match the document".into()))
+}
+
+fn signature(document: &Value) -> Result<LinkedDataSignature> {
+    let signature = document
+        .get("signature")
+        .ok_or_else(|| Error::Signature("document has no signature".into()))?;
+    Ok(serde_json::from_value(signature.clone())?)
+}
+
+///////////////////////////
+// Helpers
+///////////////////////////
+/// The data that is signed: the hash of the signature options followed by the
+/// hash of the document without its signature, both hex encoded.
+fn signed_data(document: &Value, signature: &Value) -> Result<String> {
+    let mut options = signature.clone();
+    let members = as_object_mut(&mut options)?;
+    for name in ["type", "id", "signatureValue"] {
+        members.remove(name);
+    }
+    members.insert("@context".into(), OPTIONS_CONTEXT.into());
+
+    let mut document = document.clone();
+    as_object_mut(&mut document)?.remove("signature");
+    Ok(format!("{}{}", hash(&options)?, hash(&document)?))
+}
+
+fn hash(document: &Value) -> Result<String> {
+    let canonical = urdna2015::canonicalize(&jsonld::to_rdf(document)?)?;
+    Ok(format!("{:x}", Sha256::digest(canonical.as_bytes())))
+}
+
+impl<T: DeserializeOwned + Serialize> Document<T> {
+    /// The document with an `RsaSignature2017` signature by `key`. See
+    /// [sign].
+    pub fn with_signature(&self, key: &SigningKey) -> Result<Self> {
+        let mut value = serde_json::to_value(self)?;
+        sign(&mut value, key)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Verifies the signature of the document as serialized. See [verify];
+    /// prefer verifying the JSON that was received.
+    pub fn verify_signature(&self, key: &PublicKeyInfo) -> Result<()> {
+        verify(&serde_json::to_value(self)?, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::activity::Activity;
+    use crate::keys::KeyPair;
+    use crate::signature::tests::PRIVATE_KEY_PEM;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    const KEY_ID: &str = "https://mastodon.example/users/alice#main-key";
+
+    fn create() -> Value {
+        json!({
+          "@context": [
+            "https://www.w3.org/ns/activitystreams",
+            "https://w3id.org/security/v1",
+            { "toot": "http://joinmastodon.org/ns#", "sensitive": "as:sensitive" }
+          ],
+          "id": "https://mastodon.example/users/alice/statuses/1/activity",
+          "type": "Create",
+          "actor": "https://mastodon.example/users/alice",
+          "published": "2024-05-01T12:00:00Z",
+          "to": ["https://www.w3.org/ns/activitystreams#Public"],
+          "object": {
+            "id": "https://mastodon.example/users/alice/statuses/1",
+            "type": "Note",
+            "attributedTo": "https://mastodon.example/users/alice",
+            "content": "<p>Hello world</p>",
+            "sensitive": false,
+            "tag": [{ "type": "Hashtag", "name": "#hello" }]
+          }
+        })
+    }
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_pem(KEY_ID.into(), PRIVATE_KEY_PEM).unwrap()
+    }
+
+    fn public_key_info() -> PublicKeyInfo {
+        KeyPair::from_pem(PRIVATE_KEY_PEM)
+            .unwrap()
+            .public_key_info(KEY_ID.into(), "https://mastodon.example/users/alice".into())
+            .unwrap()
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let mut document = create();
+        sign(&mut document, &signing_key()).unwrap();
+        assert_eq!(document["signature"]["type"], TYPE);
+        assert_eq!(creator(&document).unwrap(), KEY_ID);
+        verify(&document, &public_key_info()).unwrap();
+
+        // The signature covers the data, not its JSON serialization.
+        let mut reshaped = document.clone();
+        reshaped["to"] = json!("https://www.w3.org/ns/activitystreams#Public");
+        reshaped["object"]["tag"] = json!({ "type": "Hashtag", "name": "#hello" });
+        verify(&reshaped, &public_key_info()).unwrap();
+    }
+
+    #[test]
+    fn tampered_document() {
+        let mut document = create();
+        sign(&mut document, &signing_key()).unwrap();
+
+        let mut tampered = document.clone();
+        tampered["object"]["content"] = json!("<p>Goodbye world</p>");
+        assert!(matches!(
+            verify(&tampered, &public_key_info()),
+            Err(Error::Signature(message)) if message == "signature doesn't match the document"
+        ));
+
+        let mut tampered = document.clone();
+        tampered["object"]["sensitive"] = json!(true);
+        assert!(verify(&tampered, &public_key_info()).is_err());
+
+        let mut tampered = document.clone();
+        tampered["signature"]["created"] = json!("2020-01-01T00:00:00Z");
+        assert!(verify(&tampered, &public_key_info()).is_err());
+
+        let mut other_key = public_key_info();
+        other_key.id = "https://mastodon.example/users/bob#main-key".into();
+        assert!(verify(&document, &other_key).is_err());
+        assert!(verify(&create(), &public_key_info()).is_err());
+    }
+
+    #[test]
+    fn incomplete_signatures() {
+        let mut document = create();
+        sign(&mut document, &signing_key()).unwrap();
+
+        for field in ["creator", "signatureValue"] {
+            let mut incomplete = document.clone();
+            incomplete["signature"]
+                .as_object_mut()
+                .unwrap()
+                .remove(field);
+            let activity: Document<Activity> =
+                Document::deserialize_string(incomplete.to_string()).unwrap();
+            assert!(activity.object.base.signature.is_some());
+            assert!(matches!(
+                verify(&incomplete, &public_key_info()),
+                Err(Error::Signature(message)) if message == format!("signature has no {field}")
+            ));
+        }
+    }
+
+    #[test]
+    fn remote_contexts_are_rejected() {
+        let mut document = create();
+        document["@context"] = json!("https://example.com/unknown-context");
+        assert!(matches!(
+            sign(&mut document, &signing_key()),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn signature_shape() {
+        let created = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 1).unwrap();
+        let signature = create_signature(&create(), &signing_key(), created).unwrap();
+        assert_eq!(signature.created.as_deref(), Some("2024-05-01T12:00:01Z"));
+        assert_eq!(signature.creator.as_deref(), Some(KEY_ID));
+        assert_eq!(
+            BASE64
+                .decode(signature.signature_value.unwrap())
+                .unwrap()
+                .len(),
+            256
+        );
+    }
+
+    #[test]
+    fn sign_document() {
+        let document: Document<Activity> =
+            Document::deserialize_string(create().to_string()).unwrap();
+        let signed = document.with_signature(&signing_key()).unwrap();
+        let signature = signed.object.base.signature.as_ref().unwrap();
+        assert_eq!(signature.signature_type, TYPE);
+        signed.verify_signature(&public_key_info()).unwrap();
+    }
+}
@@ -0,0 +1,544 @@
+//! The URDNA2015 algorithm of
+//! [RDF Dataset Canonicalization](https://www.w3.org/TR/rdf-canon/): a
+//! serialization of an RDF dataset as N-Quads that doesn't depend on how its
+//! blank nodes were labelled, so that it can be hashed and signed.
+
+use std::collections::{BTreeMap, HashMap};
+
+use sha2::{Digest, Sha256};
+
+use super::jsonld::{Quad, Term, RDF_LANG_STRING, XSD_STRING};
+use crate::{Error, Result};
+
+/// The number of times blank nodes that can't be told apart by their own
+/// quads may be hashed, so that a crafted document can't make
+/// canonicalization run for ever.
+const MAX_HASH_N_DEGREE_CALLS: usize = 10_000;
+
+/// The canonical N-Quads of a dataset: one line per quad, sorted, with blank
+/// nodes labelled `_:c14n0`, `_:c14n1` and so on.
+pub fn canonicalize(quads: &[Quad]) -> Result<String> {
+    let mut state = State {
+        quads,
+        blank_node_quads: HashMap::new(),
+        canonical: Issuer::new("_:c14n"),
+        calls: 0,
+    };
+    for (i, quad) in quads.iter().enumerate() {
+        for term in [&quad.subject, &quad.object].into_iter().chain(&quad.graph) {
+            if let Term::BlankNode(id) = term {
+                let entry = state.blank_node_quads.entry(id.as_str()).or_default();
+                if entry.last() != Some(&i) {
+                    entry.push(i);
+                }
+            }
+        }
+    }
+
+    let mut hash_to_blank_nodes: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    let mut blank_nodes: Vec<_> = state.blank_node_quads.keys().copied().collect();
+    blank_nodes.sort();
+    for id in blank_nodes {
+        hash_to_blank_nodes
+            .entry(state.hash_first_degree_quads(id))
+            .or_default()
+            .push(id);
+    }
+
+    // Blank nodes with a unique hash are labelled in the order of their
+    // hashes, the others by hashing their neighbourhoods.
+    let mut shared = Vec::new();
+    for ids in hash_to_blank_nodes.values() {
+        match ids.as_slice() {
+            [id] => {
+                state.canonical.issue(id);
+            }
+            ids => shared.push(ids.to_vec()),
+        }
+    }
+    for ids in shared {
+        let mut paths = Vec::new();
+        for id in ids {
+            if state.canonical.has(id) {
+                continue;
+            }
+            let mut issuer = Issuer::new("_:b");
+            issuer.issue(id);
+            paths.push(state.hash_n_degree_quads(id, issuer)?);
+        }
+        paths.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (_, issuer) in paths {
+            for id in issuer.order {
+                state.canonical.issue(&id);
+            }
+        }
+    }
+
+    let mut lines: Vec<_> = quads
+        .iter()
+        .map(|quad| {
+            nquad(quad, |id| {
+                state
+                    .canonical
+                    .get(id)
+                    .expect("every blank node has a canonical label")
+                    .to_string()
+            })
+        })
+        .collect();
+    lines.sort();
+    lines.dedup();
+    Ok(lines.concat())
+}
+
+///////////////////////////
+// Identifier issuer
+///////////////////////////
+#[derive(Debug, Clone)]
+struct Issuer {
+    prefix: &'static str,
+    issued: HashMap<String, String>,
+    /// The blank nodes in the order they were issued identifiers.
+    order: Vec<String>,
+}
+
+impl Issuer {
+    fn new(prefix: &'static str) -> Self {
+        Issuer {
+            prefix,
+            issued: HashMap::new(),
+            order: Vec::new(),
+        }
+    }
+
+    fn issue(&mut self, id: &str) -> String {
+        if let Some(issued) = self.issued.get(id) {
+            return issued.clone();
+        }
+        let issued = format!("{}{}", self.prefix, self.order.len());
+        self.issued.insert(id.to_string(), issued.clone());
+        self.order.push(id.to_string());
+        issued
+    }
+
+    fn has(&self, id: &str) -> bool {
+        self.issued.contains_key(id)
+    }
+
+    fn get(&self, id: &str) -> Option<&str> {
+        self.issued.get(id).map(String::as_str)
+    }
+}
+
+///////////////////////////
+// Hashing
+///////////////////////////
+struct State<'a> {
+    quads: &'a [Quad],
+    blank_node_quads: HashMap<&'a str, Vec<usize>>,
+    canonical: Issuer,
+    calls: usize,
+}
+
+impl State<'_> {
+    /// The hash of the quads that mention `id`, with `id` labelled `_:a` and
+    /// other blank nodes `_:z`.
+    fn hash_first_degree_quads(&self, id: &str) -> String {
+        let mut lines: Vec<_> = self.blank_node_quads[id]
+            .iter()
+            .map(|&i| {
+                nquad(&self.quads[i], |other| {
+                    if other == id { "_:a" } else { "_:z" }.to_string()
+                })
+            })
+            .collect();
+        lines.sort();
+        sha256_hex(&lines.concat())
+    }
+
+    fn hash_related_blank_node(
+        &self,
+        related: &str,
+        quad: &Quad,
+        issuer: &Issuer,
+        position: char,
+    ) -> String {
+        let id = match self.canonical.get(related).or_else(|| issuer.get(related)) {
+            Some(id) => id.to_string(),
+            None => self.hash_first_degree_quads(related),
+        };
+        let mut input = position.to_string();
+        if position != 'g' {
+            input.push_str(&term(&quad.predicate, |id| id.to_string()));
+        }
+        input.push_str(&id);
+        sha256_hex(&input)
+    }
+
+    fn hash_n_degree_quads(&mut self, id: &str, mut issuer: Issuer) -> Result<(String, Issuer)> {
+        self.calls += 1;
+        if self.calls > MAX_HASH_N_DEGREE_CALLS {
+            return Err(Error::Validation(
+                "document has too many indistinguishable blank nodes".into(),
+            ));
+        }
+
+        let mut hash_to_related: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for &i in &self.blank_node_quads[id] {
+            let quad = &self.quads[i];
+            let components = [('s', Some(&quad.subject)), ('o', Some(&quad.object))]
+                .into_iter()
+                .chain([('g', quad.graph.as_ref())]);
+            for (position, component) in components {
+                if let Some(Term::BlankNode(related)) = component {
+                    if related != id {
+                        let hash = self.hash_related_blank_node(related, quad, &issuer, position);
+                        hash_to_related
+                            .entry(hash)
+                            .or_default()
+                            .push(related.clone());
+                    }
+                }
+            }
+        }
+
+        let mut data_to_hash = String::new();
+        for (related_hash, blank_nodes) in hash_to_related {
+            data_to_hash.push_str(&related_hash);
+            let mut chosen: Option<(String, Issuer)> = None;
+            for permutation in permutations(&blank_nodes) {
+                let longer = |path: &str, chosen: &Option<(String, Issuer)>| match chosen {
+                    Some((chosen, _)) => path.len() >= chosen.len() && path > chosen.as_str(),
+                    None => false,
+                };
+                let mut issuer_copy = issuer.clone();
+                let mut path = String::new();
+                let mut recursion = Vec::new();
+                let mut skip = false;
+                for related in &permutation {
+                    match self.canonical.get(related) {
+                        Some(canonical) => path.push_str(canonical),
+                        None => {
+                            if !issuer_copy.has(related) {
+                                recursion.push(related.clone());
+                            }
+                            path.push_str(&issuer_copy.issue(related));
+                        }
+                    }
+                    if longer(&path, &chosen) {
+                        skip = true;
+                        break;
+                    }
+                }
+                if skip {
+                    continue;
+                }
+                for related in recursion {
+                    let (hash, result_issuer) =
+                        self.hash_n_degree_quads(&related, issuer_copy.clone())?;
+                    path.push_str(&issuer_copy.issue(&related));
+                    path.push('<');
+                    path.push_str(&hash);
+                    path.push('>');
+                    issuer_copy = result_issuer;
+                    if longer(&path, &chosen) {
+                        skip = true;
+                        break;
+                    }
+                }
+                if skip {
+                    continue;
+                }
+                if chosen.as_ref().is_none_or(|(chosen, _)| path < *chosen) {
+                    chosen = Some((path, issuer_copy));
+                }
+            }
+            if let Some((path, chosen_issuer)) = chosen {
+                data_to_hash.push_str(&path);
+                issuer = chosen_issuer;
+            }
+        }
+        Ok((sha256_hex(&data_to_hash), issuer))
+    }
+}
+
+fn permutations(items: &[String]) -> Vec<Vec<String>> {
+    if items.len() <= 1 {
+        return vec![items.to_vec()];
+    }
+    let mut result = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let mut rest = items.to_vec();
+        rest.remove(i);
+        for mut permutation in permutations(&rest) {
+            permutation.insert(0, item.clone());
+            result.push(permutation);
+        }
+    }
+    result
+}
+
+fn sha256_hex(data: &str) -> String {
+    format!("{:x}", Sha256::digest(data.as_bytes()))
+}
+
+///////////////////////////
+// N-Quads
+///////////////////////////
+/// A quad as a line of canonical N-Quads, with blank nodes labelled by
+/// `label`.
+fn nquad(quad: &Quad, label: impl Fn(&str) -> String) -> String {
+    let mut line = format!(
+        "{} {} {}",
+        term(&quad.subject, &label),
+        term(&quad.predicate, &label),
+        term(&quad.object, &label)
+    );
+    if let Some(graph) = &quad.graph {
+        line.push(' ');
+        line.push_str(&term(graph, &label));
+    }
+    line.push_str(" .\n");
+    line
+}
+
+fn term(term: &Term, label: impl Fn(&str) -> String) -> String {
+    match term {
+        Term::Iri(iri) => format!("<{iri}>"),
+        Term::BlankNode(id) => label(id),
+        Term::Literal {
+            value,
+            datatype,
+            language,
+        } => {
+            let mut literal = format!("\"{}\"", escape(value));
+            match language {
+                Some(language) if datatype == RDF_LANG_STRING => {
+                    literal.push('@');
+                    literal.push_str(language);
+                }
+                _ if datatype == XSD_STRING => {}
+                _ => literal.push_str(&format!("^^<{datatype}>")),
+            }
+            literal
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\u{8}' => escaped.push_str("\\b"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '\r' => escaped.push_str("\\r"),
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c <= '\u{1f}' || c == '\u{7f}' => {
+                escaped.push_str(&format!("\\u{:04X}", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrity::jsonld::to_rdf;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn blank(id: &str) -> Term {
+        Term::BlankNode(id.into())
+    }
+
+    fn iri(iri: &str) -> Term {
+        Term::Iri(iri.into())
+    }
+
+    fn quad(subject: Term, predicate: &str, object: Term) -> Quad {
+        Quad {
+            subject,
+            predicate: iri(predicate),
+            object,
+            graph: None,
+        }
+    }
+
+    #[test]
+    fn without_blank_nodes() {
+        let document = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "id": "https://example.com/notes/1",
+          "type": "Note",
+          "content": "Line\n\"quoted\"\t\\"
+        });
+        assert_eq!(
+            canonicalize(&to_rdf(&document).unwrap()).unwrap(),
+            concat!(
+                "<https://example.com/notes/1> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <https://www.w3.org/ns/activitystreams#Note> .\n",
+                "<https://example.com/notes/1> <https://www.w3.org/ns/activitystreams#content> \"Line\\n\\\"quoted\\\"\\t\\\\\" .\n",
+            )
+        );
+    }
+
+    #[test]
+    fn labels_blank_nodes() {
+        let document = json!({
+          "@context": "https://www.w3.org/ns/activitystreams",
+          "type": "Note",
+          "attachment": { "type": "Image", "url": "https://example.com/a.png" }
+        });
+        assert_eq!(
+            canonicalize(&to_rdf(&document).unwrap()).unwrap(),
+            concat!(
+                "_:c14n0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <https://www.w3.org/ns/activitystreams#Image> .\n",
+                "_:c14n0 <https://www.w3.org/ns/activitystreams#url> <https://example.com/a.png> .\n",
+                "_:c14n1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <https://www.w3.org/ns/activitystreams#Note> .\n",
+                "_:c14n1 <https://www.w3.org/ns/activitystreams#attachment> _:c14n0 .\n",
+            )
+        );
+    }
+
+    #[test]
+    fn independent_of_labels() {
+        // Two cycles of three blank nodes, which can only be told apart by
+        // hashing their neighbourhoods.
+        let p = "https://example.com/p";
+        let dataset = |labels: [&str; 6]| {
+            let [a, b, c, d, e, f] = labels.map(blank);
+            vec![
+                quad(a.clone(), p, b.clone()),
+                quad(b, p, c.clone()),
+                quad(c, p, a),
+                quad(d.clone(), p, e.clone()),
+                quad(e, p, f.clone()),
+                quad(f, p, d),
+            ]
+        };
+        let expected = canonicalize(&dataset(["a", "b", "c", "d", "e", "f"])).unwrap();
+        assert_eq!(
+            canonicalize(&dataset(["x", "q", "m", "b", "z", "a"])).unwrap(),
+            expected
+        );
+        assert_eq!(expected.lines().count(), 6);
+        for label in ["_:c14n0", "_:c14n5"] {
+            assert!(expected.contains(label));
+        }
+    }
+
+    // The examples of the RDF Dataset Canonicalization specification
+    // (https://www.w3.org/TR/rdf-canon/#examples), whose published
+    // first degree hashes and canonical N-Quads are checked below.
+    const P: &str = "http://example.com/#p";
+    const Q: &str = "http://example.com/#q";
+
+    fn first_degree_hash(quads: &[Quad], id: &str) -> String {
+        let mut state = State {
+            quads,
+            blank_node_quads: HashMap::new(),
+            canonical: Issuer::new("_:c14n"),
+            calls: 0,
+        };
+        for (i, quad) in quads.iter().enumerate() {
+            for term in [&quad.subject, &quad.object] {
+                if let Term::BlankNode(id) = term {
+                    state
+                        .blank_node_quads
+                        .entry(id.as_str())
+                        .or_default()
+                        .push(i);
+                }
+            }
+        }
+        state.hash_first_degree_quads(id)
+    }
+
+    #[test]
+    fn spec_unique_hashes() {
+        let u = iri("http://example.com/#u");
+        let quads = vec![
+            quad(iri(P), Q, blank("e0")),
+            quad(iri(P), "http://example.com/#r", blank("e1")),
+            quad(blank("e0"), "http://example.com/#s", u.clone()),
+            quad(blank("e1"), "http://example.com/#t", u),
+        ];
+        assert_eq!(
+            first_degree_hash(&quads, "e0"),
+            "21d1dd5ba21f3dee9d76c0c00c260fa6f5d5d65315099e553026f4828d0dc77a"
+        );
+        assert_eq!(
+            first_degree_hash(&quads, "e1"),
+            "6fa0b9bdb376852b5743ff39ca4cbf7ea14d34966b2828478fbf222e7c764473"
+        );
+        assert_eq!(
+            canonicalize(&quads).unwrap(),
+            concat!(
+                "<http://example.com/#p> <http://example.com/#q> _:c14n0 .\n",
+                "<http://example.com/#p> <http://example.com/#r> _:c14n1 .\n",
+                "_:c14n0 <http://example.com/#s> <http://example.com/#u> .\n",
+                "_:c14n1 <http://example.com/#t> <http://example.com/#u> .\n",
+            )
+        );
+    }
+
+    #[test]
+    fn spec_shared_hashes() {
+        let quads = vec![
+            quad(iri(P), Q, blank("e0")),
+            quad(iri(P), Q, blank("e1")),
+            quad(blank("e0"), P, blank("e2")),
+            quad(blank("e1"), P, blank("e3")),
+            quad(blank("e2"), "http://example.com/#r", blank("e3")),
+        ];
+        let shared = "3b26142829b8887d011d779079a243bd61ab53c3990d550320a17b59ade6ba36";
+        assert_eq!(first_degree_hash(&quads, "e0"), shared);
+        assert_eq!(first_degree_hash(&quads, "e1"), shared);
+        assert_eq!(
+            first_degree_hash(&quads, "e2"),
+            "15973d39de079913dac841ac4fa8c4781c0febfba5e83e5c6e250869587f8659"
+        );
+        assert_eq!(
+            first_degree_hash(&quads, "e3"),
+            "7e790a99273eed1dc57e43205d37ce232252c85b26ca4a6ff74ff3b5aea7bccd"
+        );
+        assert_eq!(
+            canonicalize(&quads).unwrap(),
+            concat!(
+                "<http://example.com/#p> <http://example.com/#q> _:c14n2 .\n",
+                "<http://example.com/#p> <http://example.com/#q> _:c14n3 .\n",
+                "_:c14n0 <http://example.com/#r> _:c14n1 .\n",
+                "_:c14n2 <http://example.com/#p> _:c14n1 .\n",
+                "_:c14n3 <http://example.com/#p> _:c14n0 .\n",
+            )
+        );
+    }
+
+    // Canonical N-Quads escape \b, \t, \n, \f, \r, " and \ with a backslash
+    // and the other control characters as \uXXXX, in upper case; everything
+    // else, including non-ASCII characters, is written as is. Mastodon's
+    // canonicalizer, the rdf-normalize gem, does the same.
+    #[test]
+    fn escapes() {
+        assert_eq!(escape("a\u{1}\u{7f}é"), "a\\u0001\\u007Fé");
+        let escaped: Vec<String> = (0u32..0x20)
+            .chain([0x22, 0x5c, 0x7f])
+            .map(|c| escape(&char::from_u32(c).unwrap().to_string()))
+            .collect();
+        assert_eq!(
+            escaped,
+            [
+                "\\u0000", "\\u0001", "\\u0002", "\\u0003", "\\u0004", "\\u0005", "\\u0006",
+                "\\u0007", "\\b", "\\t", "\\n", "\\u000B", "\\f", "\\r", "\\u000E", "\\u000F",
+                "\\u0010", "\\u0011", "\\u0012", "\\u0013", "\\u0014", "\\u0015", "\\u0016",
+                "\\u0017", "\\u0018", "\\u0019", "\\u001A", "\\u001B", "\\u001C", "\\u001D",
+                "\\u001E", "\\u001F", "\\\"", "\\\\", "\\u007F",
+            ]
+        );
+        assert_eq!(escape("€ 🦀 \u{a0}"), "€ 🦀 \u{a0}");
+    }
+}
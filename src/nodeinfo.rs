@@ -0,0 +1,437 @@
+//! [NodeInfo](https://nodeinfo.diaspora.software/), which servers publish so
+//! that others can show what software they run and how many users they have.
+//!
+//! A server links to its [NodeInfo] documents from the [NodeInfoLinks] at
+//! [WELL_KNOWN_PATH]. [discover] follows those links; [NodeInfoLinks::new] and
+//! [NodeInfo::from_statistics] are what a server publishes about itself.
+
+use http::{header, Request};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::client::HttpClient;
+use crate::{Error, Result};
+
+/// The path of the [NodeInfoLinks] document.
+pub const WELL_KNOWN_PATH: &str = "/.well-known/nodeinfo";
+
+/// The relation of links to NodeInfo 2.0 documents.
+pub const SCHEMA_2_0: &str = "http://nodeinfo.diaspora.software/ns/schema/2.0";
+
+/// The relation of links to NodeInfo 2.1 documents.
+pub const SCHEMA_2_1: &str = "http://nodeinfo.diaspora.software/ns/schema/2.1";
+
+/// The protocol name of ActivityPub.
+pub const ACTIVITYPUB: &str = "activitypub";
+
+///////////////////////////
+// Discovery
+///////////////////////////
+/// The document at [WELL_KNOWN_PATH], linking to the server's NodeInfo
+/// documents by schema.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NodeInfoLinks {
+    #[serde(default)]
+    pub links: Vec<NodeInfoLink>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NodeInfoLink {
+    pub rel: String,
+    pub href: String,
+}
+
+impl NodeInfoLinks {
+    /// Links to the 2.0 and 2.1 documents of the server at `base_url`, served
+    /// at `/nodeinfo/2.0` and `/nodeinfo/2.1`.
+    pub fn new(base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        NodeInfoLinks {
+            links: vec![
+                NodeInfoLink {
+                    rel: SCHEMA_2_0.into(),
+                    href: format!("{base_url}/nodeinfo/2.0"),
+                },
+                NodeInfoLink {
+                    rel: SCHEMA_2_1.into(),
+                    href: format!("{base_url}/nodeinfo/2.1"),
+                },
+            ],
+        }
+    }
+
+    /// The location of the newest NodeInfo document this crate understands.
+    pub fn newest(&self) -> Option<&str> {
+        [SCHEMA_2_1, SCHEMA_2_0].into_iter().find_map(|schema| {
+            self.links
+                .iter()
+                .find(|link| link.rel.trim_end_matches('#') == schema)
+                .map(|link| link.href.as_str())
+        })
+    }
+}
+
+///////////////////////////
+// NodeInfo
+///////////////////////////
+/// A NodeInfo 2.0 or 2.1 document. The versions only differ in 2.1's
+/// `repository` and `homepage` of the software.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    pub version: String,
+
+    pub software: Software,
+
+    pub protocols: Vec<String>,
+
+    #[serde(default)]
+    pub services: Services,
+
+    pub open_registrations: bool,
+
+    pub usage: Usage,
+
+    /// Free-form data about the server, e.g. its name.
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Software {
+    pub name: String,
+
+    pub version: String,
+
+    /// Only in NodeInfo 2.1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+
+    /// Only in NodeInfo 2.1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+}
+
+/// Third party sites the server can exchange messages with.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Services {
+    #[serde(default)]
+    pub inbound: Vec<String>,
+
+    #[serde(default)]
+    pub outbound: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    #[serde(default)]
+    pub users: Users,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_posts: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_comments: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Users {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_halfyear: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_month: Option<u64>,
+}
+
+/// What an instance knows about itself, to publish as [NodeInfo].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+    pub total_users: u64,
+    pub active_users_halfyear: u64,
+    pub active_users_month: u64,
+    pub local_posts: u64,
+    pub local_comments: u64,
+    pub open_registrations: bool,
+}
+
+impl NodeInfo {
+    /// The NodeInfo 2.1 document of an ActivityPub server running `software`.
+    pub fn from_statistics(software: Software, statistics: &Statistics) -> Self {
+        NodeInfo {
+            version: "2.1".into(),
+            software,
+            protocols: vec![ACTIVITYPUB.into()],
+            services: Services::default(),
+            open_registrations: statistics.open_registrations,
+            usage: Usage {
+                users: Users {
+                    total: Some(statistics.total_users),
+                    active_halfyear: Some(statistics.active_users_halfyear),
+                    active_month: Some(statistics.active_users_month),
+                },
+                local_posts: Some(statistics.local_posts),
+                local_comments: Some(statistics.local_comments),
+            },
+            metadata: Map::new(),
+        }
+    }
+
+    /// The document as NodeInfo 2.0, without the fields 2.0 doesn't have.
+    pub fn to_version_2_0(&self) -> Self {
+        let mut document = self.clone();
+        document.version = "2.0".into();
+        document.software.repository = None;
+        document.software.homepage = None;
+        document
+    }
+
+    /// Whether the server speaks ActivityPub.
+    pub fn supports_activitypub(&self) -> bool {
+        self.protocols
+            .iter()
+            .any(|protocol| protocol == ACTIVITYPUB)
+    }
+}
+
+///////////////////////////
+// Client
+///////////////////////////
+/// Fetches the newest NodeInfo document of the server at `host`.
+pub async fn discover<C: HttpClient + ?Sized>(client: &C, host: &str) -> Result<NodeInfo> {
+    let links: NodeInfoLinks = fetch(client, &format!("https://{host}{WELL_KNOWN_PATH}")).await?;
+    let href = links
+        .newest()
+        .ok_or_else(|| Error::Validation(format!("{host} doesn't link to NodeInfo 2.0 or 2.1")))?;
+    fetch(client, href).await
+}
+
+async fn fetch<C, T>(client: &C, uri: &str) -> Result<T>
+where
+    C: HttpClient + ?Sized,
+    T: serde::de::DeserializeOwned,
+{
+    let request = Request::get(uri)
+        .header(header::ACCEPT, "application/json")
+        .body(Vec::new())
+        .map_err(|err| Error::Validation(format!("invalid NodeInfo request: {err}")))?;
+    let response = client.execute(request).await?;
+    if !response.status().is_success() {
+        return Err(Error::Http {
+            uri: uri.to_string(),
+            status: response.status(),
+        });
+    }
+    Ok(serde_json::from_slice(response.body())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use http::{Response, StatusCode};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn mastodon() -> Value {
+        json!({
+          "version": "2.0",
+          "software": { "name": "mastodon", "version": "4.2.8" },
+          "protocols": ["activitypub"],
+          "services": { "outbound": [], "inbound": [] },
+          "usage": {
+            "users": { "total": 2871, "activeMonth": 1023, "activeHalfyear": 1789 },
+            "localPosts": 391245
+          },
+          "openRegistrations": true,
+          "metadata": {
+            "nodeName": "Hachyderm",
+            "nodeDescription": "A safe space for the tech industry"
+          }
+        })
+    }
+
+    fn pleroma() -> Value {
+        json!({
+          "version": "2.1",
+          "software": {
+            "name": "pleroma",
+            "version": "2.6.2",
+            "repository": "https://git.pleroma.social/pleroma/pleroma"
+          },
+          "protocols": ["activitypub"],
+          "services": { "inbound": [], "outbound": [] },
+          "usage": { "localPosts": 10523, "users": { "total": 42 } },
+          "openRegistrations": false,
+          "metadata": {
+            "nodeName": "Pleroma",
+            "features": ["pleroma_api", "mastodon_api", "polls", "chat"],
+            "federation": { "enabled": true, "exclusions": false },
+            "postFormats": ["text/plain", "text/html", "text/markdown"],
+            "uploadLimits": { "general": 16000000, "avatar": 2000000 }
+          }
+        })
+    }
+
+    fn misskey() -> Value {
+        json!({
+          "version": "2.1",
+          "software": {
+            "name": "misskey",
+            "version": "2024.3.1",
+            "homepage": "https://misskey-hub.net/",
+            "repository": "https://github.com/misskey-dev/misskey"
+          },
+          "protocols": ["activitypub"],
+          "services": { "inbound": [], "outbound": ["atom1.0", "rss2.0"] },
+          "openRegistrations": true,
+          "usage": {
+            "users": { "total": 512, "activeHalfyear": null, "activeMonth": null },
+            "localPosts": 88213,
+            "localComments": 0
+          },
+          "metadata": {
+            "nodeName": "Misskey",
+            "maintainer": { "name": "admin", "email": null },
+            "langs": [],
+            "disableRegistration": false,
+            "themeColor": "#86b300"
+          }
+        })
+    }
+
+    #[test]
+    fn round_trip_samples() {
+        let document: NodeInfo = serde_json::from_value(mastodon()).unwrap();
+        assert_eq!(document.software.name, "mastodon");
+        assert_eq!(document.usage.users.active_month, Some(1023));
+        assert!(document.supports_activitypub());
+        assert_eq!(serde_json::to_value(&document).unwrap(), mastodon());
+
+        let document: NodeInfo = serde_json::from_value(pleroma()).unwrap();
+        assert_eq!(
+            document.software.repository.as_deref(),
+            Some("https://git.pleroma.social/pleroma/pleroma")
+        );
+        assert_eq!(document.usage.users.active_halfyear, None);
+        assert_eq!(document.metadata["features"][3], "chat");
+        assert_eq!(serde_json::to_value(&document).unwrap(), pleroma());
+
+        // Misskey reports unknown counts as null, which is read as absent.
+        let document: NodeInfo = serde_json::from_value(misskey()).unwrap();
+        assert_eq!(document.usage.users.total, Some(512));
+        assert_eq!(document.usage.users.active_month, None);
+        assert_eq!(document.services.outbound, vec!["atom1.0", "rss2.0"]);
+        let serialized = serde_json::to_value(&document).unwrap();
+        assert_eq!(serialized["usage"]["users"], json!({ "total": 512 }));
+        assert_eq!(
+            serde_json::from_value::<NodeInfo>(serialized).unwrap(),
+            document
+        );
+    }
+
+    #[test]
+    fn build_from_statistics() {
+        let software = Software {
+            name: "rustypub".into(),
+            version: "0.1.0".into(),
+            repository: Some("https://github.com/hachyserve/rustypub".into()),
+            homepage: None,
+        };
+        let statistics = Statistics {
+            total_users: 10,
+            active_users_halfyear: 8,
+            active_users_month: 5,
+            local_posts: 100,
+            local_comments: 3,
+            open_registrations: false,
+        };
+        let document = NodeInfo::from_statistics(software, &statistics);
+        assert_eq!(
+            serde_json::to_value(&document).unwrap(),
+            json!({
+              "version": "2.1",
+              "software": {
+                "name": "rustypub",
+                "version": "0.1.0",
+                "repository": "https://github.com/hachyserve/rustypub"
+              },
+              "protocols": ["activitypub"],
+              "services": { "inbound": [], "outbound": [] },
+              "openRegistrations": false,
+              "usage": {
+                "users": { "total": 10, "activeHalfyear": 8, "activeMonth": 5 },
+                "localPosts": 100,
+                "localComments": 3
+              },
+              "metadata": {}
+            })
+        );
+
+        let version_2_0 = document.to_version_2_0();
+        assert_eq!(version_2_0.version, "2.0");
+        assert_eq!(version_2_0.software.repository, None);
+    }
+
+    #[test]
+    fn links() {
+        let links = NodeInfoLinks::new("https://example.social/");
+        assert_eq!(
+            serde_json::to_value(&links).unwrap(),
+            json!({
+              "links": [
+                {
+                  "rel": "http://nodeinfo.diaspora.software/ns/schema/2.0",
+                  "href": "https://example.social/nodeinfo/2.0"
+                },
+                {
+                  "rel": "http://nodeinfo.diaspora.software/ns/schema/2.1",
+                  "href": "https://example.social/nodeinfo/2.1"
+                }
+              ]
+            })
+        );
+        assert_eq!(links.newest(), Some("https://example.social/nodeinfo/2.1"));
+
+        let only_2_0: NodeInfoLinks = serde_json::from_value(json!({
+          "links": [
+            { "rel": "http://nodeinfo.diaspora.software/ns/schema/1.0", "href": "https://a.example/1.0" },
+            { "rel": "http://nodeinfo.diaspora.software/ns/schema/2.0", "href": "https://a.example/2.0" }
+          ]
+        }))
+        .unwrap();
+        assert_eq!(only_2_0.newest(), Some("https://a.example/2.0"));
+        assert_eq!(NodeInfoLinks::default().newest(), None);
+    }
+
+    struct FakeServer;
+
+    #[async_trait]
+    impl HttpClient for FakeServer {
+        async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+            let body = match request.uri().path() {
+                WELL_KNOWN_PATH => {
+                    serde_json::to_vec(&NodeInfoLinks::new("https://example.social"))
+                }
+                "/nodeinfo/2.1" => serde_json::to_vec(&misskey()),
+                _ => {
+                    return Ok(Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Vec::new())
+                        .unwrap())
+                }
+            };
+            Ok(Response::new(body.unwrap()))
+        }
+    }
+
+    #[tokio::test]
+    async fn discover_newest() {
+        let document = discover(&FakeServer, "example.social").await.unwrap();
+        assert_eq!(document.software.name, "misskey");
+    }
+}
@@ -0,0 +1,672 @@
+//! Client to server interactions: what a server does with what a client
+//! posts to an actor's outbox.
+//!
+//! [Outbox::post] normalizes the posted activity the way the spec requires: a
+//! bare object is wrapped in a `Create`, addressing is copied between a
+//! `Create` and its object, new ids are assigned and `bto` and `bcc` are
+//! stripped. It stores nothing itself: it returns the [SideEffect]s of the
+//! activity for the caller to persist, along with the activity to deliver.
+
+use std::fmt;
+
+use chrono::Utc;
+use rand::Rng;
+use serde_json::{Map, Value};
+
+use crate::core::activity::{Activity, ActivityBuilder, ActivityType};
+use crate::core::actor::Actor;
+use crate::core::{Document, Extensions, Object, ObjectOrLink, OneOrMany, Tombstone};
+use crate::{Error, Result};
+
+///////////////////////////
+// Side effects
+///////////////////////////
+/// A change that posting an activity makes to what the server stores.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SideEffect {
+    /// Store a new object.
+    Create(Box<Object>),
+    /// Merge properties into the stored object `id`, see [merge].
+    Update {
+        id: String,
+        properties: Extensions,
+    },
+    /// Replace a stored object with a [Tombstone].
+    Delete {
+        tombstone: Box<Tombstone>,
+    },
+    /// Add an item to a collection, e.g. a liked object to the actor's
+    /// `liked`.
+    Add {
+        collection: String,
+        item: String,
+    },
+    /// Remove an item from a collection.
+    Remove {
+        collection: String,
+        item: String,
+    },
+    /// Record a request to follow `object`. It's added to the actor's
+    /// `following` only once the request is accepted.
+    Follow {
+        object: String,
+    },
+    /// Stop following `object`, or withdraw the request to.
+    Unfollow {
+        object: String,
+    },
+    /// Block `object`: the `Block` isn't delivered to it.
+    Block {
+        object: String,
+    },
+    Unblock {
+        object: String,
+    },
+    /// Undo the activity `activity`. The activity wasn't embedded in the
+    /// `Undo`, so the caller must look up what to undo.
+    Undo {
+        activity: String,
+    },
+}
+
+/// Merges the properties of a partial update into an object: each replaces
+/// the object's value, and `null` removes it.
+pub fn merge(object: &mut Value, properties: &Extensions) {
+    let Some(object) = object.as_object_mut() else {
+        return;
+    };
+    for (name, value) in properties {
+        if value.is_null() {
+            object.remove(name);
+        } else {
+            object.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+/// The result of [Outbox::post].
+#[derive(Debug, Clone)]
+pub struct Posted {
+    /// The activity to store and show: without `bto` or `bcc`.
+    pub activity: Document<Activity>,
+    /// The activity to deliver, which still has its `bto` and `bcc` so that
+    /// they're delivered to; [Deliverer](crate::delivery::Deliverer) removes
+    /// them from what it sends. `None` for activities that aren't delivered,
+    /// like `Block`.
+    pub delivery: Option<Document<Activity>>,
+    pub side_effects: Vec<SideEffect>,
+}
+
+///////////////////////////
+// Outbox
+///////////////////////////
+pub struct Outbox {
+    /// The actor whose outbox this is.
+    pub actor: Actor,
+    /// Mints the id of a new activity or object, given its type.
+    pub mint_id: Box<dyn Fn(&str) -> String + Send + Sync>,
+    /// Whether the actor may update or delete the object with an id. By
+    /// default, objects with ids minted by the default `mint_id`, i.e. under
+    /// the actor's id; with other ids, look up the `attributedTo` of the
+    /// stored object instead.
+    pub owns: Box<dyn Fn(&str) -> bool + Send + Sync>,
+}
+
+impl fmt::Debug for Outbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Outbox")
+            .field("actor", &self.actor)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Outbox {
+    /// The outbox of `actor`, minting ids like
+    /// `https://example.social/users/alice/note/5f0c6a3e9b1d2c47`.
+    pub fn new(actor: Actor) -> Result<Self> {
+        let actor_id = actor
+            .base
+            .id
+            .clone()
+            .ok_or_else(|| Error::Validation("actor has no id".into()))?;
+        let prefix = format!("{actor_id}/");
+        let owns = move |id: &str| id.starts_with(&prefix);
+        let mint_id = move |object_type: &str| {
+            let suffix: u64 = rand::thread_rng().gen();
+            format!(
+                "{actor_id}/{}/{suffix:016x}",
+                object_type.to_ascii_lowercase()
+            )
+        };
+        Ok(Outbox {
+            actor,
+            mint_id: Box::new(mint_id),
+            owns: Box::new(owns),
+        })
+    }
+
+    fn actor_id(&self) -> &str {
+        self.actor.base.id.as_deref().unwrap_or_default()
+    }
+
+    /// Processes the JSON a client posted: an activity, or an object to wrap
+    /// in a `Create`.
+    pub fn post(&self, document: Value) -> Result<Posted> {
+        let object_type = document
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::Validation("posted object has no type".into()))?;
+        if let ActivityType::Other(_) = ActivityType::from(object_type) {
+            self.post_object(serde_json::from_value(document)?)
+        } else {
+            // Updates are read from the JSON, where null properties survive.
+            let properties = document.get("object").and_then(Value::as_object).cloned();
+            self.process(serde_json::from_value(document)?, properties)
+        }
+    }
+
+    /// Wraps an object in a `Create` and processes that.
+    pub fn post_object(&self, document: Document<Object>) -> Result<Posted> {
+        let object = document.object;
+        let create = ActivityBuilder::default()
+            .with_base(|base| {
+                base.object_type(Some(ActivityType::Create.to_string()))
+                    .to(object.to.clone())
+                    .bto(object.bto.clone())
+                    .cc(object.cc.clone())
+                    .bcc(object.bcc.clone())
+                    .audience(object.audience.clone())
+            })
+            .object(Some(OneOrMany::One(ObjectOrLink::object(object))))
+            .build()?;
+        self.process(Document::new(document.context, create), None)
+    }
+
+    /// Processes an activity. An `Update` can't remove properties of known
+    /// types this way, since they have no `null`: [Outbox::post] its JSON.
+    pub fn post_activity(&self, document: Document<Activity>) -> Result<Posted> {
+        let properties = serde_json::to_value(&document.object)?
+            .get("object")
+            .and_then(Value::as_object)
+            .cloned();
+        self.process(document, properties)
+    }
+
+    fn process(
+        &self,
+        mut document: Document<Activity>,
+        properties: Option<Map<String, Value>>,
+    ) -> Result<Posted> {
+        let actor_id = self.actor_id().to_string();
+        let activity = &mut document.object;
+        let activity_type = activity
+            .activity_type()
+            .ok_or_else(|| Error::Validation("activity has no type".into()))?;
+
+        match &activity.actor {
+            None => activity.actor = Some(OneOrMany::One(ObjectOrLink::iri(actor_id.clone()))),
+            Some(actors) => {
+                if let Some(other) = actors.iter().find(|actor| actor.id() != Some(&actor_id)) {
+                    return Err(Error::Validation(format!(
+                        "{actor_id} can't post an activity by {}",
+                        other.id().unwrap_or_default()
+                    )));
+                }
+            }
+        }
+        // Whatever id the client gave is ignored.
+        activity.base.id = Some((self.mint_id)(activity_type.as_str()));
+        activity.base.published.get_or_insert_with(Utc::now);
+
+        let side_effects = match activity_type {
+            ActivityType::Create => self.create(activity)?,
+            ActivityType::Update => {
+                let id = self.owned_object(activity)?;
+                let mut properties = properties.ok_or_else(|| {
+                    Error::Validation(format!("Update of {id} has no embedded object"))
+                })?;
+                properties.remove("id");
+                vec![SideEffect::Update { id, properties }]
+            }
+            ActivityType::Delete => {
+                let id = self.owned_object(activity)?;
+                vec![SideEffect::Delete {
+                    tombstone: Box::new(tombstone(activity, id)),
+                }]
+            }
+            ActivityType::Follow => vec![SideEffect::Follow {
+                object: object_id(activity)?,
+            }],
+            ActivityType::Like => vec![SideEffect::Add {
+                collection: self.liked()?,
+                item: object_id(activity)?,
+            }],
+            ActivityType::Block => vec![SideEffect::Block {
+                object: object_id(activity)?,
+            }],
+            ActivityType::Add | ActivityType::Remove => {
+                let collection = activity
+                    .target
+                    .as_ref()
+                    .and_then(OneOrMany::first)
+                    .and_then(ObjectOrLink::id)
+                    .ok_or_else(|| Error::Validation(format!("{activity_type} has no target")))?
+                    .to_string();
+                let item = object_id(activity)?;
+                if activity_type == ActivityType::Add {
+                    vec![SideEffect::Add { collection, item }]
+                } else {
+                    vec![SideEffect::Remove { collection, item }]
+                }
+            }
+            ActivityType::Undo => self.undo(activity, &actor_id)?,
+            _ => Vec::new(),
+        };
+
+        let delivery = (activity_type != ActivityType::Block).then(|| document.clone());
+        let activity = &mut document.object;
+        strip_blind_recipients(&mut activity.base);
+        for object in activity.object.iter_mut().flat_map(OneOrMany::iter_mut) {
+            if let ObjectOrLink::Object(object) = object {
+                strip_blind_recipients(object);
+            }
+        }
+        Ok(Posted {
+            activity: document,
+            delivery,
+            side_effects,
+        })
+    }
+
+    /// Assigns ids to the objects of a `Create` and copies addressing between
+    /// them and the activity.
+    fn create(&self, activity: &mut Activity) -> Result<Vec<SideEffect>> {
+        let actor_id = self.actor_id();
+        let objects = activity
+            .object
+            .as_mut()
+            .ok_or_else(|| Error::Validation("Create has no object".into()))?;
+        let mut side_effects = Vec::new();
+        for object in objects.iter_mut() {
+            let ObjectOrLink::Object(object) = object else {
+                return Err(Error::Validation(
+                    "the object of a Create must be embedded".into(),
+                ));
+            };
+            let object_type = object.object_type.as_deref().unwrap_or("object");
+            object.id = Some((self.mint_id)(object_type));
+            object
+                .attributed_to
+                .get_or_insert_with(|| OneOrMany::One(ObjectOrLink::iri(actor_id)));
+            object.published = object.published.or(activity.base.published);
+
+            let base = &mut activity.base;
+            for (ours, theirs) in [
+                (&mut base.to, &mut object.to),
+                (&mut base.bto, &mut object.bto),
+                (&mut base.cc, &mut object.cc),
+                (&mut base.bcc, &mut object.bcc),
+                (&mut base.audience, &mut object.audience),
+            ] {
+                let merged = merge_addresses(ours, theirs);
+                *ours = merged.clone();
+                *theirs = merged;
+            }
+            side_effects.push(SideEffect::Create(object.clone()));
+        }
+        Ok(side_effects)
+    }
+
+    fn undo(&self, activity: &Activity, actor_id: &str) -> Result<Vec<SideEffect>> {
+        let undone = activity
+            .object
+            .as_ref()
+            .and_then(OneOrMany::first)
+            .ok_or_else(|| Error::Validation("Undo has no object".into()))?;
+        let ObjectOrLink::Object(undone) = undone else {
+            let id = undone.id().unwrap_or_default().to_string();
+            return Ok(vec![SideEffect::Undo { activity: id }]);
+        };
+
+        // Only the embedded activity's type matters here, so it's reread as
+        // an activity.
+        let undone: Activity = serde_json::from_value(serde_json::to_value(undone)?)?;
+        if let Some(actors) = &undone.actor {
+            if actors.iter().any(|actor| actor.id() != Some(actor_id)) {
+                return Err(Error::Validation(format!(
+                    "{actor_id} can't undo the activities of others"
+                )));
+            }
+        }
+        Ok(match undone.activity_type() {
+            Some(ActivityType::Follow) => vec![SideEffect::Unfollow {
+                object: object_id(&undone)?,
+            }],
+            Some(ActivityType::Like) => vec![SideEffect::Remove {
+                collection: self.liked()?,
+                item: object_id(&undone)?,
+            }],
+            Some(ActivityType::Block) => vec![SideEffect::Unblock {
+                object: object_id(&undone)?,
+            }],
+            _ => match undone.base.id {
+                Some(id) => vec![SideEffect::Undo { activity: id }],
+                None => return Err(Error::Validation("undone activity has no id".into())),
+            },
+        })
+    }
+
+    /// The id of the object of an activity, which the actor must own, see
+    /// [Outbox::owns]. The actor itself counts too.
+    fn owned_object(&self, activity: &Activity) -> Result<String> {
+        let id = object_id(activity)?;
+        if id != self.actor_id() && !(self.owns)(&id) {
+            return Err(Error::Validation(format!(
+                "{} can't change {id}",
+                self.actor_id()
+            )));
+        }
+        Ok(id)
+    }
+
+    fn liked(&self) -> Result<String> {
+        self.actor.liked.clone().ok_or_else(|| {
+            Error::Validation(format!("{} has no liked collection", self.actor_id()))
+        })
+    }
+}
+
+///////////////////////////
+// Helpers
+///////////////////////////
+fn object_id(activity: &Activity) -> Result<String> {
+    activity
+        .object
+        .as_ref()
+        .and_then(OneOrMany::first)
+        .and_then(ObjectOrLink::id)
+        .map(String::from)
+        .ok_or_else(|| Error::Validation("activity has no object id".into()))
+}
+
+/// What a deleted object is replaced with.
+fn tombstone(activity: &Activity, id: String) -> Tombstone {
+    let former_type = activity
+        .object
+        .as_ref()
+        .and_then(OneOrMany::first)
+        .and_then(ObjectOrLink::as_object)
+        .and_then(|object| object.object_type.clone());
+    let deleted = activity.base.published.unwrap_or_else(Utc::now);
+    Tombstone::new(id, former_type.map(OneOrMany::One), deleted)
+}
+
+/// The union of two sets of addresses, in order.
+fn merge_addresses(
+    a: &Option<OneOrMany<ObjectOrLink>>,
+    b: &Option<OneOrMany<ObjectOrLink>>,
+) -> Option<OneOrMany<ObjectOrLink>> {
+    let mut merged: Vec<ObjectOrLink> = Vec::new();
+    for address in a.iter().chain(b.iter()).flat_map(OneOrMany::iter) {
+        if !merged.iter().any(|seen| seen.id() == address.id()) {
+            merged.push(address.clone());
+        }
+    }
+    match merged.len() {
+        0 => None,
+        1 => merged.pop().map(OneOrMany::One),
+        _ => Some(OneOrMany::Many(merged)),
+    }
+}
+
+fn strip_blind_recipients(object: &mut Object) {
+    object.bto = None;
+    object.bcc = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::PUBLIC;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    const ALICE: &str = "https://local.example/users/alice";
+
+    fn with_context(mut document: Value) -> Value {
+        document["@context"] = json!("https://www.w3.org/ns/activitystreams");
+        document
+    }
+
+    fn outbox() -> Outbox {
+        let actor: Actor = serde_json::from_value(json!({
+          "id": ALICE,
+          "type": "Person",
+          "inbox": "https://local.example/users/alice/inbox",
+          "liked": "https://local.example/users/alice/liked"
+        }))
+        .unwrap();
+        let mut outbox = Outbox::new(actor).unwrap();
+        outbox.mint_id = Box::new(|object_type| format!("{ALICE}/{object_type}/1"));
+        // As if looking up the attributedTo of stored notes.
+        outbox.owns = Box::new(|id| id == "https://local.example/notes/1");
+        outbox
+    }
+
+    #[test]
+    fn wrap_object_in_create() {
+        let posted = outbox()
+            .post(with_context(json!({
+              "id": "https://local.example/made-up",
+              "type": "Note",
+              "content": "Hello",
+              "to": PUBLIC,
+              "bcc": "https://remote.example/users/bob"
+            })))
+            .unwrap();
+
+        let activity = serde_json::to_value(&posted.activity).unwrap();
+        assert_eq!(activity["id"], format!("{ALICE}/Create/1"));
+        assert_eq!(activity["type"], "Create");
+        assert_eq!(activity["actor"], ALICE);
+        assert_eq!(activity["to"], PUBLIC);
+        assert!(activity.get("bcc").is_none());
+        let object = &activity["object"];
+        assert_eq!(object["id"], format!("{ALICE}/Note/1"));
+        assert_eq!(object["attributedTo"], ALICE);
+        assert_eq!(object["published"], activity["published"]);
+        assert!(object.get("bcc").is_none());
+
+        let delivery = posted.delivery.unwrap();
+        assert_eq!(
+            delivery.object.base.bcc,
+            Some(OneOrMany::One(ObjectOrLink::iri(
+                "https://remote.example/users/bob"
+            )))
+        );
+        match &posted.side_effects[..] {
+            [SideEffect::Create(note)] => {
+                assert_eq!(note.content.as_deref(), Some("Hello"));
+                assert!(note.bcc.is_some());
+            }
+            side_effects => panic!("unexpected side effects {side_effects:?}"),
+        }
+    }
+
+    #[test]
+    fn create_copies_addressing() {
+        let posted = outbox()
+            .post(with_context(json!({
+              "type": "Create",
+              "to": PUBLIC,
+              "cc": ["https://local.example/users/alice/followers"],
+              "object": {
+                "type": "Note",
+                "content": "Hello",
+                "cc": ["https://remote.example/users/bob"]
+              }
+            })))
+            .unwrap();
+        let activity = serde_json::to_value(&posted.activity).unwrap();
+        let cc = json!([
+            "https://local.example/users/alice/followers",
+            "https://remote.example/users/bob"
+        ]);
+        assert_eq!(activity["cc"], cc);
+        assert_eq!(activity["object"]["cc"], cc);
+        assert_eq!(activity["object"]["to"], PUBLIC);
+
+        assert!(matches!(
+            outbox().post(with_context(
+                json!({ "type": "Create", "object": "https://local.example/notes/1" })
+            )),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            outbox().post(with_context(json!({
+              "type": "Create",
+              "actor": "https://remote.example/users/bob",
+              "object": { "type": "Note" }
+            }))),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn update_and_delete() {
+        let posted = outbox()
+            .post(with_context(json!({
+              "type": "Update",
+              "object": {
+                "id": "https://local.example/notes/1",
+                "content": "Hello again",
+                "summary": null
+              }
+            })))
+            .unwrap();
+        let properties = match &posted.side_effects[..] {
+            [SideEffect::Update { id, properties }] => {
+                assert_eq!(id, "https://local.example/notes/1");
+                properties.clone()
+            }
+            side_effects => panic!("unexpected side effects {side_effects:?}"),
+        };
+        let mut note = json!({
+          "id": "https://local.example/notes/1",
+          "type": "Note",
+          "summary": "CW",
+          "content": "Hello"
+        });
+        merge(&mut note, &properties);
+        assert_eq!(
+            note,
+            json!({
+              "id": "https://local.example/notes/1",
+              "type": "Note",
+              "content": "Hello again"
+            })
+        );
+
+        let posted = outbox()
+            .post(with_context(json!({
+              "type": "Delete",
+              "object": { "id": "https://local.example/notes/1", "type": "Note" }
+            })))
+            .unwrap();
+        let SideEffect::Delete { tombstone } = &posted.side_effects[0] else {
+            panic!("unexpected side effects {:?}", posted.side_effects);
+        };
+        assert_eq!(
+            tombstone.base.id.as_deref(),
+            Some("https://local.example/notes/1")
+        );
+        assert_eq!(tombstone.former_type, Some("Note".into()));
+        assert!(tombstone.deleted.is_some());
+
+        assert!(matches!(
+            outbox().post(with_context(
+                json!({ "type": "Delete", "object": "https://remote.example/notes/1" })
+            )),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn other_local_actors_objects_refused() {
+        // Bob's note is on Alice's server, but isn't hers.
+        let bobs_note = "https://local.example/notes/2";
+        for activity_type in ["Update", "Delete"] {
+            let result = outbox().post(with_context(json!({
+              "type": activity_type,
+              "object": { "id": bobs_note, "type": "Note" }
+            })));
+            match result {
+                Err(Error::Validation(message)) => {
+                    assert_eq!(message, format!("{ALICE} can't change {bobs_note}"))
+                }
+                result => panic!("unexpected result {result:?}"),
+            }
+        }
+
+        // By default, only objects under the actor's id are hers.
+        let mut outbox = outbox();
+        outbox.owns = Outbox::new(outbox.actor.clone()).unwrap().owns;
+        let delete =
+            |object: &str| outbox.post(with_context(json!({ "type": "Delete", "object": object })));
+        assert!(delete("https://local.example/users/bob/note/1").is_err());
+        assert!(delete("https://local.example/users/alice2/note/1").is_err());
+        assert!(delete(&format!("{ALICE}/note/1")).is_ok());
+    }
+
+    #[test]
+    fn collection_side_effects() {
+        let side_effects =
+            |activity: Value| outbox().post(with_context(activity)).unwrap().side_effects;
+        let bob = "https://remote.example/users/bob";
+        let note = "https://remote.example/notes/1";
+
+        assert_eq!(
+            side_effects(json!({ "type": "Follow", "object": bob })),
+            vec![SideEffect::Follow { object: bob.into() }]
+        );
+        assert_eq!(
+            side_effects(json!({ "type": "Like", "object": note })),
+            vec![SideEffect::Add {
+                collection: "https://local.example/users/alice/liked".into(),
+                item: note.into()
+            }]
+        );
+        assert_eq!(
+            side_effects(json!({
+              "type": "Undo",
+              "object": { "type": "Like", "actor": ALICE, "object": note }
+            })),
+            vec![SideEffect::Remove {
+                collection: "https://local.example/users/alice/liked".into(),
+                item: note.into()
+            }]
+        );
+        assert_eq!(
+            side_effects(json!({
+              "type": "Undo",
+              "object": { "type": "Follow", "object": bob }
+            })),
+            vec![SideEffect::Unfollow { object: bob.into() }]
+        );
+        assert_eq!(
+            side_effects(json!({ "type": "Undo", "object": "https://local.example/follows/1" })),
+            vec![SideEffect::Undo {
+                activity: "https://local.example/follows/1".into()
+            }]
+        );
+
+        let block = outbox()
+            .post(with_context(json!({ "type": "Block", "object": bob })))
+            .unwrap();
+        assert_eq!(
+            block.side_effects,
+            vec![SideEffect::Block { object: bob.into() }]
+        );
+        assert!(block.delivery.is_none());
+    }
+}
@@ -0,0 +1,829 @@
+//! The side effects of activities between servers: the state changes the
+//! spec mandates when an activity is received, or accepted locally.
+//!
+//! [SideEffects::apply] runs the usual state machine against an
+//! [ObjectStore]: a `Follow` is a pending request until it's accepted, after
+//! which the follower is in the followee's `followers` (and the followee in
+//! the follower's `following`); `Reject` or `Undo` end it. `Like` and
+//! `Announce` are added to the `likes` and `shares` of their object, and
+//! `Undo` takes them out again. `Create` and `Update` store the objects of
+//! their actor, `Delete` replaces them with a tombstone.
+//!
+//! `Accept`, `Reject` and `Undo` act on the stored activity they refer to,
+//! never on a copy embedded in them, and only its actor may undo it.
+//!
+//! Only the collections of actors and objects in the store are changed, so
+//! applying the same activities on the follower's and the followee's server
+//! keeps both sides of the relationship.
+
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::core::activity::{Activity, ActivityType};
+use crate::core::{ObjectOrLink, OneOrMany};
+use crate::fetch::same_origin;
+use crate::store::{is_tombstone, ObjectStore};
+use crate::{Error, Result};
+
+/// The collection of the `Follow` requests `actor` has yet to answer.
+pub fn follow_requests(actor: &str) -> String {
+    format!("{actor}/follow_requests")
+}
+
+/// A change [SideEffects::apply] made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// An object was stored, by a `Create` or `Update`.
+    Stored(String),
+    /// An object was replaced with a tombstone.
+    Deleted(String),
+    /// `follower` asked to follow `followee` with the `Follow` activity
+    /// `follow`, which is in the followee's [follow_requests].
+    FollowRequested {
+        follow: String,
+        follower: String,
+        followee: String,
+    },
+    Followed {
+        follower: String,
+        followee: String,
+    },
+    /// `follower` no longer follows `followee`, or asks to.
+    Unfollowed {
+        follower: String,
+        followee: String,
+    },
+    /// An activity was added to a collection, e.g. a `Like` to `likes`.
+    Added {
+        collection: String,
+        item: String,
+    },
+    Removed {
+        collection: String,
+        item: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct SideEffects<S> {
+    pub store: S,
+}
+
+impl<S: ObjectStore> SideEffects<S> {
+    pub fn new(store: S) -> Self {
+        SideEffects { store }
+    }
+
+    /// Applies the side effects of an activity, whose actor has been
+    /// authenticated, returning what changed.
+    pub async fn apply(&self, activity: &Activity) -> Result<Vec<Effect>> {
+        let actor = single_actor(activity)?;
+        let Some(activity_type) = activity.activity_type() else {
+            return Ok(Vec::new());
+        };
+        match activity_type {
+            ActivityType::Create => self.store_objects(activity, actor, false).await,
+            ActivityType::Update => self.store_objects(activity, actor, true).await,
+            ActivityType::Delete => self.delete(activity, actor).await,
+            ActivityType::Follow => self.follow(activity, actor).await,
+            ActivityType::Accept | ActivityType::Reject => {
+                let Some(follow) = self.stored_activity(object(activity)?).await? else {
+                    return Ok(Vec::new());
+                };
+                if follow.activity_type() != Some(ActivityType::Follow) {
+                    return Ok(Vec::new());
+                }
+                let followee = object_id(&follow)?;
+                if followee != actor {
+                    return Err(Error::Validation(format!(
+                        "{actor} can't answer a follow request to {followee}"
+                    )));
+                }
+                if activity_type == ActivityType::Reject {
+                    // Followees may also reject a follow they accepted.
+                    return self.unfollow(&follow).await;
+                }
+                let pending = self
+                    .store
+                    .contains(&follow_requests(followee), activity_id(&follow)?)
+                    .await?;
+                if !pending {
+                    return Ok(Vec::new());
+                }
+                self.accept(&follow).await
+            }
+            ActivityType::Like => self.count(activity, "likes").await,
+            ActivityType::Announce => self.count(activity, "shares").await,
+            ActivityType::Undo => self.undo(activity, actor).await,
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    async fn store_objects(
+        &self,
+        activity: &Activity,
+        actor: &str,
+        update: bool,
+    ) -> Result<Vec<Effect>> {
+        let mut effects = Vec::new();
+        let embedded = activity
+            .object
+            .iter()
+            .flat_map(OneOrMany::iter)
+            .filter_map(ObjectOrLink::as_object);
+        for object in embedded {
+            let Some(id) = object.id.as_deref() else {
+                continue;
+            };
+            let stored = self.store.get(id).await?;
+            check_owner(actor, id, stored.as_ref())?;
+            match &stored {
+                // Deleted objects stay deleted.
+                Some(stored) if is_tombstone(stored) => continue,
+                // Updates replace objects, and only those we have.
+                None if update => continue,
+                _ => {}
+            }
+            self.store.put(serde_json::to_value(object)?).await?;
+            effects.push(Effect::Stored(id.to_string()));
+        }
+        Ok(effects)
+    }
+
+    async fn delete(&self, activity: &Activity, actor: &str) -> Result<Vec<Effect>> {
+        let id = object_id(activity)?;
+        let Some(stored) = self.store.get(id).await? else {
+            return Ok(Vec::new());
+        };
+        check_owner(actor, id, Some(&stored))?;
+        if is_tombstone(&stored) {
+            return Ok(Vec::new());
+        }
+        let deleted = activity.base.published.unwrap_or_else(Utc::now);
+        self.store.tombstone(id, deleted).await?;
+        Ok(vec![Effect::Deleted(id.to_string())])
+    }
+
+    /// Records a `Follow` as pending, whether it's to a stored actor or by
+    /// one, so that only an answer to it is accepted.
+    async fn follow(&self, follow: &Activity, follower: &str) -> Result<Vec<Effect>> {
+        let id = activity_id(follow)?;
+        let followee = object_id(follow)?;
+        self.put_activity(follow, follower).await?;
+        if self.store.get(followee).await?.is_none() && self.store.get(follower).await?.is_none() {
+            return Ok(Vec::new());
+        }
+        self.store.append(&follow_requests(followee), id).await?;
+        Ok(vec![Effect::FollowRequested {
+            follow: id.to_string(),
+            follower: follower.to_string(),
+            followee: followee.to_string(),
+        }])
+    }
+
+    async fn accept(&self, follow: &Activity) -> Result<Vec<Effect>> {
+        let follower = single_actor(follow)?;
+        let followee = object_id(follow)?;
+        if let Ok(id) = activity_id(follow) {
+            self.store.remove(&follow_requests(followee), id).await?;
+        }
+        let mut changed = false;
+        if let Some(followers) = self.collection(followee, "followers").await? {
+            changed |= self.store.append(&followers, follower).await?;
+        }
+        if let Some(following) = self.collection(follower, "following").await? {
+            changed |= self.store.append(&following, followee).await?;
+        }
+        Ok(changed
+            .then(|| Effect::Followed {
+                follower: follower.to_string(),
+                followee: followee.to_string(),
+            })
+            .into_iter()
+            .collect())
+    }
+
+    async fn unfollow(&self, follow: &Activity) -> Result<Vec<Effect>> {
+        let follower = single_actor(follow)?;
+        let followee = object_id(follow)?;
+        let mut changed = false;
+        if let Ok(id) = activity_id(follow) {
+            changed |= self.store.remove(&follow_requests(followee), id).await?;
+        }
+        if let Some(followers) = self.collection(followee, "followers").await? {
+            changed |= self.store.remove(&followers, follower).await?;
+        }
+        if let Some(following) = self.collection(follower, "following").await? {
+            changed |= self.store.remove(&following, followee).await?;
+        }
+        Ok(changed
+            .then(|| Effect::Unfollowed {
+                follower: follower.to_string(),
+                followee: followee.to_string(),
+            })
+            .into_iter()
+            .collect())
+    }
+
+    /// Adds a `Like` or `Announce` to the `likes` or `shares` of its object.
+    async fn count(&self, activity: &Activity, name: &str) -> Result<Vec<Effect>> {
+        let id = activity_id(activity)?;
+        self.put_activity(activity, single_actor(activity)?).await?;
+        let Some(collection) = self.collection(object_id(activity)?, name).await? else {
+            return Ok(Vec::new());
+        };
+        if !self.store.append(&collection, id).await? {
+            return Ok(Vec::new());
+        }
+        Ok(vec![Effect::Added {
+            collection,
+            item: id.to_string(),
+        }])
+    }
+
+    async fn undo(&self, undo: &Activity, actor: &str) -> Result<Vec<Effect>> {
+        let Some(undone) = self.stored_activity(object(undo)?).await? else {
+            return Ok(Vec::new());
+        };
+        let undone_actor = single_actor(&undone)?;
+        if undone_actor != actor {
+            return Err(Error::Validation(format!(
+                "{actor} can't undo an activity of {undone_actor}"
+            )));
+        }
+        let name = match undone.activity_type() {
+            Some(ActivityType::Follow) => return self.unfollow(&undone).await,
+            Some(ActivityType::Like) => "likes",
+            Some(ActivityType::Announce) => "shares",
+            _ => return Ok(Vec::new()),
+        };
+        let id = activity_id(&undone)?;
+        let Some(collection) = self.collection(object_id(&undone)?, name).await? else {
+            return Ok(Vec::new());
+        };
+        if !self.store.remove(&collection, id).await? {
+            return Ok(Vec::new());
+        }
+        Ok(vec![Effect::Removed {
+            collection,
+            item: id.to_string(),
+        }])
+    }
+
+    /// Stores an activity of `actor`, unless another actor's activity is
+    /// stored with its id.
+    async fn put_activity(&self, activity: &Activity, actor: &str) -> Result<()> {
+        let id = activity_id(activity)?;
+        if let Some(stored) = self.store.get(id).await? {
+            let stored_actor = stored
+                .get("actor")
+                .and_then(|stored_actor| match stored_actor {
+                    Value::String(stored_actor) => Some(stored_actor.as_str()),
+                    stored_actor => stored_actor.get("id").and_then(Value::as_str),
+                });
+            if stored_actor != Some(actor) {
+                return Err(Error::Validation(format!("{actor} can't replace {id}")));
+            }
+        }
+        self.store.put(serde_json::to_value(activity)?).await
+    }
+
+    /// The activity an `Accept`, `Reject` or `Undo` refers to, as stored: an
+    /// embedded copy is only used for its id, since the sender controls it.
+    async fn stored_activity(&self, object: &ObjectOrLink) -> Result<Option<Activity>> {
+        let Some(id) = object.id() else {
+            return Ok(None);
+        };
+        match self.store.get(id).await? {
+            Some(value) if !is_tombstone(&value) => Ok(Some(serde_json::from_value(value)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The id of the collection `name`, e.g. `followers`, of a stored object.
+    async fn collection(&self, id: &str, name: &str) -> Result<Option<String>> {
+        let Some(object) = self.store.get(id).await? else {
+            return Ok(None);
+        };
+        Ok(object.get(name).and_then(value_id).map(String::from))
+    }
+}
+
+///////////////////////////
+// Helpers
+///////////////////////////
+fn single_actor(activity: &Activity) -> Result<&str> {
+    let actors = activity
+        .actor
+        .as_ref()
+        .ok_or_else(|| Error::Validation("activity has no actor".into()))?;
+    match actors.as_slice() {
+        [actor] => actor
+            .id()
+            .ok_or_else(|| Error::Validation("actor has no id".into())),
+        _ => Err(Error::Validation(
+            "activities by several actors aren't supported".into(),
+        )),
+    }
+}
+
+fn activity_id(activity: &Activity) -> Result<&str> {
+    activity
+        .base
+        .id
+        .as_deref()
+        .ok_or_else(|| Error::Validation("activity has no id".into()))
+}
+
+fn object(activity: &Activity) -> Result<&ObjectOrLink> {
+    activity
+        .object
+        .as_ref()
+        .and_then(OneOrMany::first)
+        .ok_or_else(|| Error::Validation("activity has no object".into()))
+}
+
+fn object_id(activity: &Activity) -> Result<&str> {
+    object(activity)?
+        .id()
+        .ok_or_else(|| Error::Validation("object has no id".into()))
+}
+
+/// The id of a JSON value referring to an object: an IRI, or an embedded
+/// object with an id.
+fn value_id(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(id) => Some(id),
+        value => value.get("id").and_then(Value::as_str),
+    }
+}
+
+/// Checks that `actor` may change the object `id`: it's on the actor's
+/// origin and, if stored, attributed to the actor or the actor itself.
+fn check_owner(actor: &str, id: &str, stored: Option<&Value>) -> Result<()> {
+    let forbidden = || Error::Validation(format!("{actor} can't change {id}"));
+    if !same_origin(actor, id) {
+        return Err(forbidden());
+    }
+    let attributed_to = stored
+        .and_then(|stored| stored.get("attributedTo"))
+        .map(|attributed_to| match attributed_to {
+            Value::Array(owners) => owners.iter().any(|owner| value_id(owner) == Some(actor)),
+            owner => value_id(owner) == Some(actor),
+        });
+    match attributed_to {
+        Some(false) if id != actor => Err(forbidden()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    const ALICE: &str = "https://local.example/users/alice";
+    const BOB: &str = "https://remote.example/users/bob";
+    const NOTE: &str = "https://local.example/notes/1";
+
+    fn actor(id: &str) -> Value {
+        json!({
+          "id": id,
+          "type": "Person",
+          "inbox": format!("{id}/inbox"),
+          "followers": format!("{id}/followers"),
+          "following": format!("{id}/following")
+        })
+    }
+
+    async fn side_effects() -> SideEffects<MemoryStore> {
+        let store = MemoryStore::new();
+        store.put(actor(ALICE)).await.unwrap();
+        store
+            .put(json!({
+              "id": NOTE,
+              "type": "Note",
+              "attributedTo": ALICE,
+              "likes": format!("{NOTE}/likes"),
+              "shares": { "id": format!("{NOTE}/shares"), "type": "Collection" }
+            }))
+            .await
+            .unwrap();
+        SideEffects::new(store)
+    }
+
+    fn activity(value: Value) -> Activity {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn follow() -> Value {
+        json!({
+          "id": "https://remote.example/follows/1",
+          "type": "Follow",
+          "actor": BOB,
+          "object": ALICE
+        })
+    }
+
+    #[tokio::test]
+    async fn follow_accept_undo() {
+        let side_effects = side_effects().await;
+        let store = &side_effects.store;
+        let followers = format!("{ALICE}/followers");
+
+        let effects = side_effects.apply(&activity(follow())).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::FollowRequested {
+                follow: "https://remote.example/follows/1".into(),
+                follower: BOB.into(),
+                followee: ALICE.into(),
+            }]
+        );
+        assert!(!store.contains(&followers, BOB).await.unwrap());
+
+        // Bob can't accept his own request.
+        let forged = json!({
+          "id": "https://remote.example/accepts/1",
+          "type": "Accept",
+          "actor": BOB,
+          "object": "https://remote.example/follows/1"
+        });
+        assert!(side_effects.apply(&activity(forged)).await.is_err());
+
+        let accept = json!({
+          "id": "https://local.example/accepts/1",
+          "type": "Accept",
+          "actor": ALICE,
+          "object": "https://remote.example/follows/1"
+        });
+        let effects = side_effects.apply(&activity(accept)).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::Followed {
+                follower: BOB.into(),
+                followee: ALICE.into(),
+            }]
+        );
+        assert!(store.contains(&followers, BOB).await.unwrap());
+        assert!(!store
+            .contains(&follow_requests(ALICE), "https://remote.example/follows/1")
+            .await
+            .unwrap());
+
+        let undo = json!({
+          "id": "https://remote.example/undos/1",
+          "type": "Undo",
+          "actor": BOB,
+          "object": follow()
+        });
+        let effects = side_effects.apply(&activity(undo)).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::Unfollowed {
+                follower: BOB.into(),
+                followee: ALICE.into(),
+            }]
+        );
+        assert!(!store.contains(&followers, BOB).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn follow_rejected() {
+        let side_effects = side_effects().await;
+        side_effects.apply(&activity(follow())).await.unwrap();
+        let reject = json!({
+          "id": "https://local.example/rejects/1",
+          "type": "Reject",
+          "actor": ALICE,
+          "object": follow()
+        });
+        let effects = side_effects.apply(&activity(reject)).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::Unfollowed {
+                follower: BOB.into(),
+                followee: ALICE.into(),
+            }]
+        );
+        assert!(!side_effects
+            .store
+            .contains(&format!("{ALICE}/followers"), BOB)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn outgoing_follow_accepted() {
+        // Alice follows Bob, and Bob's server accepts.
+        let side_effects = side_effects().await;
+        let follow = json!({
+          "id": "https://local.example/follows/1",
+          "type": "Follow",
+          "actor": ALICE,
+          "object": BOB
+        });
+        let effects = side_effects.apply(&activity(follow.clone())).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::FollowRequested {
+                follow: "https://local.example/follows/1".into(),
+                follower: ALICE.into(),
+                followee: BOB.into(),
+            }]
+        );
+        let accept = json!({
+          "id": "https://remote.example/accepts/1",
+          "type": "Accept",
+          "actor": BOB,
+          "object": follow
+        });
+        side_effects.apply(&activity(accept)).await.unwrap();
+        assert!(side_effects
+            .store
+            .contains(&format!("{ALICE}/following"), BOB)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn forged_follow_not_accepted() {
+        // Bob claims Alice asked to follow him, which she never did.
+        let side_effects = side_effects().await;
+        let accept = json!({
+          "id": "https://remote.example/accepts/1",
+          "type": "Accept",
+          "actor": BOB,
+          "object": {
+            "id": "https://local.example/follows/1",
+            "type": "Follow",
+            "actor": ALICE,
+            "object": BOB
+          }
+        });
+        assert!(side_effects
+            .apply(&activity(accept))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Nor does storing a follow of his own making help.
+        let create = json!({
+          "id": "https://remote.example/creates/1",
+          "type": "Create",
+          "actor": BOB,
+          "object": {
+            "id": "https://remote.example/follows/1",
+            "type": "Follow",
+            "actor": ALICE,
+            "object": BOB
+          }
+        });
+        side_effects.apply(&activity(create)).await.unwrap();
+        let accept = json!({
+          "id": "https://remote.example/accepts/2",
+          "type": "Accept",
+          "actor": BOB,
+          "object": "https://remote.example/follows/1"
+        });
+        assert!(side_effects
+            .apply(&activity(accept))
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(!side_effects
+            .store
+            .contains(&format!("{ALICE}/following"), BOB)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn forged_undo_of_like() {
+        let side_effects = side_effects().await;
+        let likes = format!("{NOTE}/likes");
+        let mallorys_like = json!({
+          "id": "https://remote.example/likes/1",
+          "type": "Like",
+          "actor": "https://remote.example/users/mallory",
+          "object": NOTE
+        });
+        side_effects
+            .apply(&activity(mallorys_like.clone()))
+            .await
+            .unwrap();
+
+        // Bob embeds Mallory's like as his own.
+        let mut forged = mallorys_like;
+        forged["actor"] = json!(BOB);
+        let undo = json!({
+          "id": "https://remote.example/undos/1",
+          "type": "Undo",
+          "actor": BOB,
+          "object": forged
+        });
+        assert!(side_effects.apply(&activity(undo)).await.is_err());
+        // Or sends it as a like of his own.
+        assert!(side_effects.apply(&activity(forged)).await.is_err());
+        assert!(side_effects
+            .store
+            .contains(&likes, "https://remote.example/likes/1")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn like_and_unlike() {
+        let side_effects = side_effects().await;
+        let store = &side_effects.store;
+        let likes = format!("{NOTE}/likes");
+        let like = json!({
+          "id": "https://remote.example/likes/1",
+          "type": "Like",
+          "actor": BOB,
+          "object": NOTE
+        });
+
+        let effects = side_effects.apply(&activity(like.clone())).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::Added {
+                collection: likes.clone(),
+                item: "https://remote.example/likes/1".into(),
+            }]
+        );
+        // Delivered twice, counted once.
+        assert!(side_effects
+            .apply(&activity(like))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Only Bob can undo his like.
+        let mut undo = json!({
+          "id": "https://remote.example/undos/1",
+          "type": "Undo",
+          "actor": "https://remote.example/users/mallory",
+          "object": "https://remote.example/likes/1"
+        });
+        assert!(side_effects.apply(&activity(undo.clone())).await.is_err());
+        assert!(store
+            .contains(&likes, "https://remote.example/likes/1")
+            .await
+            .unwrap());
+
+        undo["actor"] = json!(BOB);
+        let effects = side_effects.apply(&activity(undo)).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::Removed {
+                collection: likes.clone(),
+                item: "https://remote.example/likes/1".into(),
+            }]
+        );
+        assert!(!store
+            .contains(&likes, "https://remote.example/likes/1")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn announce() {
+        let side_effects = side_effects().await;
+        let announce = json!({
+          "id": "https://remote.example/announces/1",
+          "type": "Announce",
+          "actor": BOB,
+          "object": NOTE
+        });
+        let effects = side_effects.apply(&activity(announce)).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::Added {
+                collection: format!("{NOTE}/shares"),
+                item: "https://remote.example/announces/1".into(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn create_update_delete() {
+        let side_effects = side_effects().await;
+        let store = &side_effects.store;
+        let note = json!({
+          "id": "https://remote.example/notes/1",
+          "type": "Note",
+          "attributedTo": BOB,
+          "content": "Hello"
+        });
+        let create = json!({
+          "id": "https://remote.example/creates/1",
+          "type": "Create",
+          "actor": BOB,
+          "object": note
+        });
+        let effects = side_effects.apply(&activity(create)).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::Stored("https://remote.example/notes/1".into())]
+        );
+
+        let mut update = json!({
+          "id": "https://remote.example/updates/1",
+          "type": "Update",
+          "actor": BOB,
+          "object": note
+        });
+        update["object"]["content"] = json!("Hello again");
+        side_effects.apply(&activity(update)).await.unwrap();
+        let stored = store
+            .get("https://remote.example/notes/1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored["content"], "Hello again");
+
+        // Bob can't delete Alice's note.
+        let mut delete = json!({
+          "id": "https://remote.example/deletes/1",
+          "type": "Delete",
+          "actor": BOB,
+          "object": NOTE
+        });
+        assert!(side_effects.apply(&activity(delete.clone())).await.is_err());
+
+        delete["object"] = json!("https://remote.example/notes/1");
+        let effects = side_effects.apply(&activity(delete)).await.unwrap();
+        assert_eq!(
+            effects,
+            vec![Effect::Deleted("https://remote.example/notes/1".into())]
+        );
+        let stored = store
+            .get("https://remote.example/notes/1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored["type"], "Tombstone");
+        assert_eq!(stored["formerType"], "Note");
+
+        // And it stays deleted.
+        let create = json!({
+          "id": "https://remote.example/creates/2",
+          "type": "Create",
+          "actor": BOB,
+          "object": note
+        });
+        assert!(side_effects
+            .apply(&activity(create))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn embedded_attribution() {
+        let side_effects = side_effects().await;
+        let store = &side_effects.store;
+        let mallory = "https://remote.example/users/mallory";
+        let notes = [
+            (
+                "https://remote.example/notes/1",
+                json!({ "type": "Person", "id": BOB }),
+            ),
+            (
+                "https://remote.example/notes/2",
+                json!([{ "type": "Person", "id": BOB }, "https://remote.example/users/carol"]),
+            ),
+        ];
+        for (n, (id, attributed_to)) in notes.into_iter().enumerate() {
+            store
+                .put(json!({ "id": id, "type": "Note", "attributedTo": attributed_to }))
+                .await
+                .unwrap();
+            let update = |actor: &str| {
+                activity(json!({
+                  "id": format!("https://remote.example/updates/{n}"),
+                  "type": "Update",
+                  "actor": actor,
+                  "object": { "id": id, "type": "Note", "content": "Edited" }
+                }))
+            };
+            assert!(side_effects.apply(&update(mallory)).await.is_err());
+            side_effects.apply(&update(BOB)).await.unwrap();
+            assert_eq!(store.get(id).await.unwrap().unwrap()["content"], "Edited");
+
+            let delete = json!({
+              "id": format!("https://remote.example/deletes/{n}"),
+              "type": "Delete",
+              "actor": BOB,
+              "object": id
+            });
+            assert_eq!(
+                side_effects.apply(&activity(delete)).await.unwrap(),
+                vec![Effect::Deleted(id.into())]
+            );
+        }
+    }
+}
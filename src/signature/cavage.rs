@@ -0,0 +1,698 @@
+//! Signatures as specified by
+//! [draft-cavage-http-signatures-12](https://datatracker.ietf.org/doc/html/draft-cavage-http-signatures-12),
+//! the scheme Mastodon sends and expects. The request body is covered through
+//! a `Digest` header.
+
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use http::{header, Method, Request};
+use rsa::{
+    pkcs1v15,
+    signature::{SignatureEncoding, Signer, Verifier as _},
+};
+use sha2::Sha256;
+
+use super::{header_value, sha256_base64, to_header_value, unix_time, SigningKey};
+use crate::core::actor::PublicKeyInfo;
+use crate::{Error, Result};
+
+/// The algorithm used to sign requests. `hs2019` is accepted as well when
+/// verifying, in which case the algorithm is derived from the key.
+pub const ALGORITHM: &str = "rsa-sha256";
+
+/// The headers covered by a signature, `digest` being added when the request
+/// has a body.
+pub const DEFAULT_HEADERS: &[&str] = &["(request-target)", "host", "date"];
+
+///////////////////////////
+// Signature header
+///////////////////////////
+/// The parameters of a `Signature` header, e.g.
+/// `keyId="https://example.com/users/alice#main-key",algorithm="rsa-sha256",headers="(request-target) host date digest",signature="..."`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureHeader {
+    pub key_id: String,
+    pub algorithm: Option<String>,
+    /// The lowercased names of the covered headers, in signing order.
+    pub headers: Vec<String>,
+    pub signature: Vec<u8>,
+    pub created: Option<u64>,
+    pub expires: Option<u64>,
+}
+
+impl SignatureHeader {
+    pub fn parse(value: &str) -> Result<Self> {
+        let mut key_id = None;
+        let mut algorithm = None;
+        let mut headers = None;
+        let mut signature = None;
+        let mut created = None;
+        let mut expires = None;
+
+        for (name, value) in parse_params(value)? {
+            match name.as_str() {
+                "keyId" => key_id = Some(value),
+                "algorithm" => algorithm = Some(value.to_lowercase()),
+                "headers" => {
+                    headers = Some(value.split_whitespace().map(str::to_lowercase).collect())
+                }
+                "signature" => {
+                    signature =
+                        Some(BASE64.decode(value).map_err(|_| {
+                            Error::Signature("signature is not valid base64".into())
+                        })?)
+                }
+                "created" => created = Some(parse_timestamp(&name, &value)?),
+                "expires" => expires = Some(parse_timestamp(&name, &value)?),
+                // Unknown parameters must be ignored.
+                _ => {}
+            }
+        }
+
+        Ok(SignatureHeader {
+            key_id: key_id.ok_or_else(|| missing_param("keyId"))?,
+            algorithm,
+            // The specification defaults to `(created)` alone.
+            headers: headers.unwrap_or_else(|| vec!["(created)".into()]),
+            signature: signature.ok_or_else(|| missing_param("signature"))?,
+            created,
+            expires,
+        })
+    }
+
+    /// The signature of a request, from its `Signature` header or an
+    /// `Authorization: Signature ...` header.
+    pub fn from_request<B>(request: &Request<B>) -> Result<Self> {
+        if let Some(value) = header_value(request.headers(), "signature")? {
+            return SignatureHeader::parse(&value);
+        }
+        match header_value(request.headers(), header::AUTHORIZATION.as_str())? {
+            Some(value) if value.starts_with("Signature ") => {
+                SignatureHeader::parse(&value["Signature ".len()..])
+            }
+            _ => Err(Error::Signature("request is not signed".into())),
+        }
+    }
+}
+
+impl fmt::Display for SignatureHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "keyId=\"{}\"", self.key_id)?;
+        if let Some(algorithm) = &self.algorithm {
+            write!(f, ",algorithm=\"{algorithm}\"")?;
+        }
+        if let Some(created) = self.created {
+            write!(f, ",created={created}")?;
+        }
+        if let Some(expires) = self.expires {
+            write!(f, ",expires={expires}")?;
+        }
+        write!(
+            f,
+            ",headers=\"{}\",signature=\"{}\"",
+            self.headers.join(" "),
+            BASE64.encode(&self.signature)
+        )
+    }
+}
+
+fn parse_params(value: &str) -> Result<Vec<(String, String)>> {
+    let mut params = Vec::new();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let (name, after) = rest
+            .split_once('=')
+            .ok_or_else(|| Error::Signature(format!("malformed signature parameters: {value}")))?;
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').ok_or_else(|| {
+                    Error::Signature(format!("unterminated signature parameter: {name}"))
+                })?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => after.split_at(after.find(',').unwrap_or(after.len())),
+        };
+        params.push((name.trim().to_string(), value.to_string()));
+        rest = after.trim_start().trim_start_matches(',').trim_start();
+    }
+    Ok(params)
+}
+
+fn parse_timestamp(name: &str, value: &str) -> Result<u64> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| Error::Signature(format!("{name} is not a timestamp: {value}")))
+}
+
+fn missing_param(name: &str) -> Error {
+    Error::Signature(format!("signature has no {name}"))
+}
+
+///////////////////////////
+// Signing
+///////////////////////////
+/// Signs a request with [DEFAULT_HEADERS], plus `digest` if it has a body or
+/// is a POST.
+///
+/// `Host` and `Date` are added if they're missing, as is `Digest`. The
+/// signature goes into the `Signature` header.
+pub fn sign<B: AsRef<[u8]>>(request: &mut Request<B>, key: &SigningKey) -> Result<()> {
+    let mut headers: Vec<&str> = DEFAULT_HEADERS.to_vec();
+    if !request.body().as_ref().is_empty() || request.method() == Method::POST {
+        headers.push("digest");
+    }
+    sign_headers(request, key, &headers)
+}
+
+/// Signs the given headers of a request. See [sign].
+pub fn sign_headers<B: AsRef<[u8]>>(
+    request: &mut Request<B>,
+    key: &SigningKey,
+    headers: &[&str],
+) -> Result<()> {
+    let headers: Vec<String> = headers.iter().map(|name| name.to_lowercase()).collect();
+    if headers.iter().any(|name| name == "host") && !request.headers().contains_key(header::HOST) {
+        let host = request
+            .uri()
+            .authority()
+            .ok_or_else(|| Error::Signature("request has no host".into()))?
+            .to_string();
+        request
+            .headers_mut()
+            .insert(header::HOST, to_header_value(&host)?);
+    }
+    if headers.iter().any(|name| name == "date") && !request.headers().contains_key(header::DATE) {
+        let date = httpdate::fmt_http_date(SystemTime::now());
+        request
+            .headers_mut()
+            .insert(header::DATE, to_header_value(&date)?);
+    }
+    if headers.iter().any(|name| name == "digest") {
+        let digest = format!("SHA-256={}", sha256_base64(request.body().as_ref()));
+        request
+            .headers_mut()
+            .insert("digest", to_header_value(&digest)?);
+    }
+
+    let mut signature = SignatureHeader {
+        key_id: key.key_id.clone(),
+        algorithm: Some(ALGORITHM.into()),
+        headers,
+        signature: Vec::new(),
+        created: None,
+        expires: None,
+    };
+    let signing_string = signing_string(request, &signature)?;
+    let signer = pkcs1v15::SigningKey::<Sha256>::new(key.private_key.clone());
+    signature.signature = signer
+        .try_sign(signing_string.as_bytes())
+        .map_err(|err| Error::Signature(format!("failed to sign request: {err}")))?
+        .to_vec();
+
+    let value = to_header_value(&signature.to_string())?;
+    request.headers_mut().insert("signature", value);
+    Ok(())
+}
+
+/// The string that is signed: one `name: value` line per covered header.
+pub fn signing_string<B>(request: &Request<B>, signature: &SignatureHeader) -> Result<String> {
+    let lines = signature
+        .headers
+        .iter()
+        .map(|name| {
+            let value = match name.as_str() {
+                "(request-target)" => format!(
+                    "{} {}",
+                    request.method().as_str().to_lowercase(),
+                    request
+                        .uri()
+                        .path_and_query()
+                        .map(|path| path.as_str())
+                        .unwrap_or("/")
+                ),
+                "(created)" => signature
+                    .created
+                    .ok_or_else(|| missing_param("created"))?
+                    .to_string(),
+                "(expires)" => signature
+                    .expires
+                    .ok_or_else(|| missing_param("expires"))?
+                    .to_string(),
+                "host" => match header_value(request.headers(), name)? {
+                    Some(host) => host,
+                    None => request
+                        .uri()
+                        .authority()
+                        .map(|authority| authority.to_string())
+                        .ok_or_else(|| Error::Signature("request has no host".into()))?,
+                },
+                _ => header_value(request.headers(), name)?
+                    .ok_or_else(|| Error::Signature(format!("signed header {name} is missing")))?,
+            };
+            Ok(format!("{name}: {value}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(lines.join("\n"))
+}
+
+///////////////////////////
+// Verification
+///////////////////////////
+/// Checks the signature of incoming requests.
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    /// How old the `Date` of a request may be.
+    pub max_age: Duration,
+    /// How far in the future the `Date` of a request may be.
+    pub clock_skew: Duration,
+    /// Headers a signature must cover. `digest` is required as well when the
+    /// request has a body.
+    pub required_headers: Vec<String>,
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Verifier {
+            max_age: Duration::from_secs(12 * 60 * 60),
+            clock_skew: Duration::from_secs(60 * 60),
+            required_headers: DEFAULT_HEADERS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl Verifier {
+    /// Verifies that `request` was signed by `key`, that the signature covers
+    /// the required headers, that the body matches its `Digest` and that the
+    /// request is recent.
+    pub fn verify<B: AsRef<[u8]>>(&self, request: &Request<B>, key: &PublicKeyInfo) -> Result<()> {
+        self.verify_at(request, key, SystemTime::now())
+    }
+
+    /// [Verifier::verify], taking `now` as the current time.
+    pub fn verify_at<B: AsRef<[u8]>>(
+        &self,
+        request: &Request<B>,
+        key: &PublicKeyInfo,
+        now: SystemTime,
+    ) -> Result<()> {
+        let signature = SignatureHeader::from_request(request)?;
+        if signature.key_id != key.id {
+            return Err(Error::Signature(format!(
+                "request is signed with {} but the key is {}",
+                signature.key_id, key.id
+            )));
+        }
+        match signature.algorithm.as_deref() {
+            None | Some(ALGORITHM) | Some("hs2019") => {}
+            Some(algorithm) => {
+                return Err(Error::Signature(format!(
+                    "unsupported algorithm {algorithm}"
+                )))
+            }
+        }
+
+        let covers = |name: &str| signature.headers.iter().any(|header| header == name);
+        if let Some(missing) = self.required_headers.iter().find(|name| !covers(name)) {
+            return Err(Error::Signature(format!(
+                "signature doesn't cover {missing}"
+            )));
+        }
+
+        self.check_time(request, &signature, now)?;
+        check_digest(request, covers("digest"))?;
+
+        let signing_string = signing_string(request, &signature)?;
+        let verifier = pkcs1v15::VerifyingKey::<Sha256>::new(key.public_key()?);
+        let bytes = pkcs1v15::Signature::try_from(signature.signature.as_slice())
+            .map_err(|_| Error::Signature("malformed signature".into()))?;
+        verifier
+            .verify(signing_string.as_bytes(), &bytes)
+            .map_err(|_| Error::Signature("signature doesn't match".into()))
+    }
+
+    /// Checks that the request is recent, going by the signed `(created)`
+    /// or else the signed `Date`. Unsigned times are ignored, since anyone
+    /// can change them.
+    fn check_time<B>(
+        &self,
+        request: &Request<B>,
+        signature: &SignatureHeader,
+        now: SystemTime,
+    ) -> Result<()> {
+        let covers = |name: &str| signature.headers.iter().any(|header| header == name);
+        let created = signature
+            .created
+            .filter(|_| covers("(created)"))
+            .map(unix_time)
+            .transpose()?;
+        let date = match header_value(request.headers(), header::DATE.as_str())? {
+            Some(date) if covers("date") => Some(
+                httpdate::parse_http_date(&date)
+                    .map_err(|_| Error::Signature(format!("invalid date: {date}")))?,
+            ),
+            _ => None,
+        };
+        let signed_at = created.or(date).ok_or_else(|| {
+            Error::Signature("signature covers neither a date nor a creation time".into())
+        })?;
+
+        if signed_at > now + self.clock_skew {
+            return Err(Error::Signature("request is dated in the future".into()));
+        }
+        if now
+            .duration_since(signed_at)
+            .is_ok_and(|age| age > self.max_age)
+        {
+            return Err(Error::Signature("request is too old".into()));
+        }
+        let expires = signature.expires.filter(|_| covers("(expires)"));
+        if let Some(expires) = expires {
+            if unix_time(expires)? < now {
+                return Err(Error::Signature("signature has expired".into()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the `Digest` header against the body. A request with a body must
+/// have a signed digest.
+fn check_digest<B: AsRef<[u8]>>(request: &Request<B>, signed: bool) -> Result<()> {
+    let body = request.body().as_ref();
+    let digest = header_value(request.headers(), "digest")?;
+    let digest = match digest {
+        Some(digest) if signed => digest,
+        Some(_) | None if body.is_empty() => return Ok(()),
+        _ => return Err(Error::Signature("body is not covered by a digest".into())),
+    };
+
+    // The header may list several digests: check the one we support.
+    let expected = sha256_base64(body);
+    let sha256 = digest
+        .split(',')
+        .filter_map(|digest| digest.trim().split_once('='))
+        .find(|(algorithm, _)| algorithm.eq_ignore_ascii_case("sha-256"))
+        .map(|(_, value)| value)
+        .ok_or_else(|| Error::Signature(format!("unsupported digest: {digest}")))?;
+    if sha256 == expected {
+        Ok(())
+    } else {
+        Err(Error::Signature("body doesn't match its digest".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::tests::{public_key_info, signing_key, KEY_ID};
+    use pretty_assertions::assert_eq;
+    use std::time::UNIX_EPOCH;
+
+    const BODY: &str = r#"{"type":"Follow","actor":"https://example.com/users/alice","object":"https://mastodon.example/users/bob"}"#;
+
+    fn post() -> Request<Vec<u8>> {
+        Request::post("https://mastodon.example/users/bob/inbox")
+            .header(header::CONTENT_TYPE, "application/activity+json")
+            .body(BODY.as_bytes().to_vec())
+            .unwrap()
+    }
+
+    fn error<T: fmt::Debug>(result: Result<T>) -> String {
+        match result.unwrap_err() {
+            Error::Signature(message) => message,
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+
+        let headers = request.headers();
+        assert_eq!(headers[header::HOST], "mastodon.example");
+        assert!(headers.contains_key(header::DATE));
+        assert_eq!(
+            headers["digest"],
+            format!("SHA-256={}", sha256_base64(BODY.as_bytes()))
+        );
+
+        let signature = SignatureHeader::from_request(&request).unwrap();
+        assert_eq!(signature.key_id, KEY_ID);
+        assert_eq!(signature.algorithm.as_deref(), Some("rsa-sha256"));
+        assert_eq!(
+            signature.headers,
+            vec!["(request-target)", "host", "date", "digest"]
+        );
+
+        Verifier::default()
+            .verify(&request, &public_key_info())
+            .unwrap();
+    }
+
+    #[test]
+    fn sign_get() {
+        let mut request = Request::get("https://mastodon.example/users/bob?page=1")
+            .body(Vec::new())
+            .unwrap();
+        sign(&mut request, &signing_key()).unwrap();
+        assert!(!request.headers().contains_key("digest"));
+
+        let signature = SignatureHeader::from_request(&request).unwrap();
+        assert!(signing_string(&request, &signature)
+            .unwrap()
+            .starts_with("(request-target): get /users/bob?page=1\nhost: mastodon.example\n"));
+        Verifier::default()
+            .verify(&request, &public_key_info())
+            .unwrap();
+    }
+
+    // Signed with `openssl dgst -sha256 -sign`, as a check against an
+    // independent implementation.
+    #[test]
+    fn verify_openssl_signature() {
+        let request = Request::post("/users/bob/inbox")
+            .header(header::HOST, "mastodon.example")
+            .header(header::DATE, "Sun, 05 Jan 2025 12:00:00 GMT")
+            .header(
+                "digest",
+                "SHA-256=LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=",
+            )
+            .header("signature", OPENSSL_SIGNATURE)
+            .body(b"hello".to_vec())
+            .unwrap();
+        let now = httpdate::parse_http_date("Sun, 05 Jan 2025 12:00:30 GMT").unwrap();
+        Verifier::default()
+            .verify_at(&request, &public_key_info(), now)
+            .unwrap();
+    }
+
+    const OPENSSL_SIGNATURE: &str = concat!(
+        r#"keyId="https://example.com/users/alice#main-key",algorithm="rsa-sha256","#,
+        r#"headers="(request-target) host date digest","#,
+        r#"signature="YmAIdQcKZSxPIwmiXy8iyeW8dxf7lpFski1b4EctfyPcFzCuld7eeXhxM4Xw2kkW1QfNl8/DFuMLeu1iAYVYwU+AjhtpPvgk11+4Mg3EtuebUPDinGo27T1IXQaZLLd1bgRU0VXGtT0BlObWupzy6soLBLmKEZ29eDPobWsYL/EIS8llwZhkxG7I6FjGU3FEq32t+mGRKvzO9Y6rqmpOJFxB3i1IeenD4kyrnZEXh8w+Na+dJd7JtLl0UQpIKRS/maM5ObP+3a2+IXpxSPHPTqsco9bCa4wy/Usaa8qbIRnRd216yosmdmtLgGCsLT+AbuN6eVW75SKk05m3Miy3gw==""#
+    );
+
+    #[test]
+    fn tampered_body() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        *request.body_mut() = BODY.replace("Follow", "Block").into_bytes();
+        assert_eq!(
+            error(Verifier::default().verify(&request, &public_key_info())),
+            "body doesn't match its digest"
+        );
+    }
+
+    #[test]
+    fn tampered_header() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        let later = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        request
+            .headers_mut()
+            .insert(header::DATE, later.parse().unwrap());
+        assert_eq!(
+            error(Verifier::default().verify(&request, &public_key_info())),
+            "signature doesn't match"
+        );
+    }
+
+    #[test]
+    fn date_skew() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        let verifier = Verifier::default();
+        let key = public_key_info();
+
+        let tomorrow = SystemTime::now() + Duration::from_secs(24 * 60 * 60);
+        assert_eq!(
+            error(verifier.verify_at(&request, &key, tomorrow)),
+            "request is too old"
+        );
+        let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+        assert_eq!(
+            error(verifier.verify_at(&request, &key, yesterday)),
+            "request is dated in the future"
+        );
+    }
+
+    #[test]
+    fn overflowing_times() {
+        let verifier = Verifier {
+            required_headers: Vec::new(),
+            ..Verifier::default()
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        for times in [
+            "created=18446744073709551615".to_string(),
+            format!("created={now},expires=18446744073709551615"),
+        ] {
+            let mut request = post();
+            request.headers_mut().insert(
+                "signature",
+                format!(
+                    r#"keyId="{KEY_ID}",algorithm="hs2019",{times},headers="(created) (expires)",signature="AQID""#
+                )
+                .parse()
+                .unwrap(),
+            );
+            assert_eq!(
+                error(verifier.verify(&request, &public_key_info())),
+                "invalid time: 18446744073709551615"
+            );
+        }
+    }
+
+    #[test]
+    fn unsigned_created() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        let key = public_key_info();
+        let tomorrow = SystemTime::now() + Duration::from_secs(24 * 60 * 60);
+
+        // A replay can't freshen the request by adding a creation time.
+        let mut signature = SignatureHeader::from_request(&request).unwrap();
+        let created = tomorrow.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        signature.created = Some(created);
+        request
+            .headers_mut()
+            .insert("signature", signature.to_string().parse().unwrap());
+        assert_eq!(
+            error(Verifier::default().verify_at(&request, &key, tomorrow)),
+            "request is too old"
+        );
+
+        // And without a signed date, there's nothing to go by.
+        let mut request = post();
+        sign_headers(
+            &mut request,
+            &signing_key(),
+            &["(request-target)", "host", "digest"],
+        )
+        .unwrap();
+        let verifier = Verifier {
+            required_headers: vec!["(request-target)".into()],
+            ..Verifier::default()
+        };
+        assert_eq!(
+            error(verifier.verify(&request, &key)),
+            "signature covers neither a date nor a creation time"
+        );
+    }
+
+    #[test]
+    fn missing_headers() {
+        let mut request = post();
+        sign_headers(
+            &mut request,
+            &signing_key(),
+            &["(request-target)", "host", "date"],
+        )
+        .unwrap();
+        assert_eq!(
+            error(Verifier::default().verify(&request, &public_key_info())),
+            "body is not covered by a digest"
+        );
+
+        let mut request = post();
+        sign_headers(
+            &mut request,
+            &signing_key(),
+            &["(request-target)", "digest"],
+        )
+        .unwrap();
+        assert_eq!(
+            error(Verifier::default().verify(&request, &public_key_info())),
+            "signature doesn't cover host"
+        );
+    }
+
+    #[test]
+    fn wrong_key() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        let mut key = public_key_info();
+        key.id = "https://example.com/users/mallory#main-key".into();
+        assert_eq!(
+            error(Verifier::default().verify(&request, &key)),
+            "request is signed with https://example.com/users/alice#main-key but the key is https://example.com/users/mallory#main-key"
+        );
+    }
+
+    #[test]
+    fn unsupported_algorithm() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        let mut signature = SignatureHeader::from_request(&request).unwrap();
+        signature.algorithm = Some("hmac-sha256".into());
+        request
+            .headers_mut()
+            .insert("signature", signature.to_string().parse().unwrap());
+        assert_eq!(
+            error(Verifier::default().verify(&request, &public_key_info())),
+            "unsupported algorithm hmac-sha256"
+        );
+    }
+
+    #[test]
+    fn parse_signature_header() {
+        let signature = SignatureHeader::parse(
+            r#"keyId="Test",algorithm="hs2019",created=1402170695, headers="(request-target) (created) Host",signature="AQID""#,
+        )
+        .unwrap();
+        assert_eq!(
+            signature,
+            SignatureHeader {
+                key_id: "Test".into(),
+                algorithm: Some("hs2019".into()),
+                headers: vec!["(request-target)".into(), "(created)".into(), "host".into()],
+                signature: vec![1, 2, 3],
+                created: Some(1402170695),
+                expires: None,
+            }
+        );
+
+        let request = Request::get("/")
+            .header(
+                header::AUTHORIZATION,
+                "Signature keyId=\"Test\",signature=\"AQID\"",
+            )
+            .body(())
+            .unwrap();
+        let signature = SignatureHeader::from_request(&request).unwrap();
+        assert_eq!(signature.headers, vec!["(created)"]);
+
+        assert!(SignatureHeader::parse("keyId=\"Test\"").is_err());
+        assert!(SignatureHeader::parse("keyId=\"Test,signature=\"AQID\"").is_err());
+    }
+}
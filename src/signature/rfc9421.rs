@@ -0,0 +1,710 @@
+//! Signatures as specified by
+//! [RFC 9421 HTTP Message Signatures](https://www.rfc-editor.org/rfc/rfc9421),
+//! carried by the `Signature-Input` and `Signature` headers. The request body
+//! is covered through a `Content-Digest` header
+//! ([RFC 9530](https://www.rfc-editor.org/rfc/rfc9530)).
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use http::{header, Method, Request};
+use rsa::{
+    pkcs1v15,
+    signature::{SignatureEncoding, Signer, Verifier as _},
+};
+use sha2::Sha256;
+
+use super::{header_value, sha256_base64, to_header_value, unix_time, SigningKey};
+use crate::core::actor::PublicKeyInfo;
+use crate::{Error, Result};
+
+/// The algorithm used to sign requests.
+pub const ALGORITHM: &str = "rsa-v1_5-sha256";
+
+/// The label of the signatures this crate creates.
+pub const LABEL: &str = "sig1";
+
+/// The components covered by a signature, `content-digest` being added when
+/// the request has a body.
+pub const DEFAULT_COMPONENTS: &[&str] = &["@method", "@target-uri"];
+
+///////////////////////////
+// Signature input
+///////////////////////////
+/// A member of the `Signature-Input` header, e.g.
+/// `sig1=("@method" "@target-uri" "content-digest");created=1704067200;keyid="https://example.com/users/alice#main-key";alg="rsa-v1_5-sha256"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureInput {
+    pub label: String,
+    /// The covered components, in signing order.
+    pub components: Vec<String>,
+    pub created: Option<u64>,
+    pub expires: Option<u64>,
+    pub key_id: Option<String>,
+    pub algorithm: Option<String>,
+    /// The value after the label, exactly as it is signed.
+    serialized: String,
+}
+
+impl SignatureInput {
+    pub fn new(
+        label: String,
+        components: Vec<String>,
+        created: Option<u64>,
+        key_id: Option<String>,
+        algorithm: Option<String>,
+    ) -> Self {
+        let mut serialized = format!(
+            "({})",
+            components
+                .iter()
+                .map(|component| format!("\"{component}\""))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        if let Some(created) = created {
+            serialized.push_str(&format!(";created={created}"));
+        }
+        if let Some(key_id) = &key_id {
+            serialized.push_str(&format!(";keyid=\"{key_id}\""));
+        }
+        if let Some(algorithm) = &algorithm {
+            serialized.push_str(&format!(";alg=\"{algorithm}\""));
+        }
+        SignatureInput {
+            label,
+            components,
+            created,
+            expires: None,
+            key_id,
+            algorithm,
+            serialized,
+        }
+    }
+
+    /// The inner list and its parameters, the value of `@signature-params`.
+    pub fn serialized(&self) -> &str {
+        &self.serialized
+    }
+
+    /// Parses all the members of a `Signature-Input` header.
+    pub fn parse(value: &str) -> Result<Vec<Self>> {
+        dictionary(value)?
+            .into_iter()
+            .map(|(label, value)| SignatureInput::parse_member(label, value))
+            .collect()
+    }
+
+    fn parse_member(label: &str, value: &str) -> Result<Self> {
+        let inner = value
+            .strip_prefix('(')
+            .and_then(|inner| inner.split_once(')'))
+            .ok_or_else(|| malformed(value))?;
+        let (list, params) = inner;
+        let components = list
+            .split_whitespace()
+            .map(|item| match unquote(item) {
+                Some(component) => Ok(component.to_lowercase()),
+                None => Err(Error::Signature(format!("unsupported component {item}"))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut input = SignatureInput {
+            label: label.to_string(),
+            components,
+            created: None,
+            expires: None,
+            key_id: None,
+            algorithm: None,
+            serialized: value.to_string(),
+        };
+        for param in split_outside_quotes(params, ';').into_iter().skip(1) {
+            let (name, value) = param.split_once('=').unwrap_or((param, "?1"));
+            match name.trim() {
+                "created" => input.created = Some(parse_integer(name, value)?),
+                "expires" => input.expires = Some(parse_integer(name, value)?),
+                "keyid" => input.key_id = Some(unquote(value).ok_or_else(|| malformed(value))?),
+                "alg" => input.algorithm = Some(unquote(value).ok_or_else(|| malformed(value))?),
+                // e.g. nonce and tag, which are signed but not interpreted.
+                _ => {}
+            }
+        }
+        Ok(input)
+    }
+
+    /// The signature inputs of a request, from its `Signature-Input` header.
+    pub fn from_request<B>(request: &Request<B>) -> Result<Vec<Self>> {
+        match header_value(request.headers(), "signature-input")? {
+            Some(value) => SignatureInput::parse(&value),
+            None => Err(Error::Signature("request is not signed".into())),
+        }
+    }
+}
+
+/// The signatures of a request by label, from its `Signature` header.
+pub fn signatures<B>(request: &Request<B>) -> Result<Vec<(String, Vec<u8>)>> {
+    let value = header_value(request.headers(), "signature")?
+        .ok_or_else(|| Error::Signature("request is not signed".into()))?;
+    dictionary(&value)?
+        .into_iter()
+        .map(|(label, value)| {
+            let bytes = value
+                .strip_prefix(':')
+                .and_then(|value| value.strip_suffix(':'))
+                .ok_or_else(|| malformed(value))?;
+            let bytes = BASE64
+                .decode(bytes)
+                .map_err(|_| Error::Signature("signature is not valid base64".into()))?;
+            Ok((label.to_string(), bytes))
+        })
+        .collect()
+}
+
+/// The `label=value` members of a structured field dictionary.
+fn dictionary(value: &str) -> Result<Vec<(&str, &str)>> {
+    split_outside_quotes(value, ',')
+        .into_iter()
+        .filter(|member| !member.is_empty())
+        .map(|member| {
+            member
+                .split_once('=')
+                .map(|(label, value)| (label.trim(), value.trim()))
+                .ok_or_else(|| malformed(member))
+        })
+        .collect()
+}
+
+/// Splits on `separator` where it's not inside a string or an inner list.
+fn split_outside_quotes(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut depth = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            c if c == separator && !quoted && depth == 0 => {
+                parts.push(value[start..i].trim());
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(value[start..].trim());
+    parts
+}
+
+/// The content of a structured field string, without escapes.
+fn unquote(value: &str) -> Option<String> {
+    let inner = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some(inner.replace("\\\"", "\"").replace("\\\\", "\\"))
+}
+
+fn parse_integer(name: &str, value: &str) -> Result<u64> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| Error::Signature(format!("{name} is not a timestamp: {value}")))
+}
+
+fn malformed(value: &str) -> Error {
+    Error::Signature(format!("malformed signature: {value}"))
+}
+
+///////////////////////////
+// Signing
+///////////////////////////
+/// Signs a request with [DEFAULT_COMPONENTS], plus `content-digest` if it
+/// has a body or is a POST.
+///
+/// `Content-Digest` is added to the request, and the signature goes into the
+/// `Signature-Input` and `Signature` headers under [LABEL].
+pub fn sign<B: AsRef<[u8]>>(request: &mut Request<B>, key: &SigningKey) -> Result<()> {
+    let mut components: Vec<&str> = DEFAULT_COMPONENTS.to_vec();
+    if !request.body().as_ref().is_empty() || request.method() == Method::POST {
+        components.push("content-digest");
+    }
+    sign_components(request, key, &components)
+}
+
+/// Signs the given components of a request. See [sign].
+pub fn sign_components<B: AsRef<[u8]>>(
+    request: &mut Request<B>,
+    key: &SigningKey,
+    components: &[&str],
+) -> Result<()> {
+    let components: Vec<String> = components.iter().map(|name| name.to_lowercase()).collect();
+    if components.iter().any(|name| name == "content-digest") {
+        let digest = format!("sha-256=:{}:", sha256_base64(request.body().as_ref()));
+        request
+            .headers_mut()
+            .insert("content-digest", to_header_value(&digest)?);
+    }
+    if components.iter().any(|name| name == "date") && !request.headers().contains_key(header::DATE)
+    {
+        let date = httpdate::fmt_http_date(SystemTime::now());
+        request
+            .headers_mut()
+            .insert(header::DATE, to_header_value(&date)?);
+    }
+
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| Error::Signature("clock is before 1970".into()))?
+        .as_secs();
+    let input = SignatureInput::new(
+        LABEL.into(),
+        components,
+        Some(created),
+        Some(key.key_id.clone()),
+        Some(ALGORITHM.into()),
+    );
+    let base = signature_base(request, &input)?;
+    let signer = pkcs1v15::SigningKey::<Sha256>::new(key.private_key.clone());
+    let signature = signer
+        .try_sign(base.as_bytes())
+        .map_err(|err| Error::Signature(format!("failed to sign request: {err}")))?;
+
+    let signature_input = format!("{}={}", input.label, input.serialized());
+    let signature = format!("{}=:{}:", input.label, BASE64.encode(signature.to_vec()));
+    let headers = request.headers_mut();
+    headers.insert("signature-input", to_header_value(&signature_input)?);
+    headers.insert("signature", to_header_value(&signature)?);
+    Ok(())
+}
+
+/// The string that is signed: one `"component": value` line per covered
+/// component, then the signature parameters.
+pub fn signature_base<B>(request: &Request<B>, input: &SignatureInput) -> Result<String> {
+    let mut lines = input
+        .components
+        .iter()
+        .map(|name| Ok(format!("\"{name}\": {}", component(request, name)?)))
+        .collect::<Result<Vec<_>>>()?;
+    lines.push(format!("\"@signature-params\": {}", input.serialized()));
+    Ok(lines.join("\n"))
+}
+
+fn component<B>(request: &Request<B>, name: &str) -> Result<String> {
+    let uri = request.uri();
+    let value = match name {
+        "@method" => request.method().as_str().to_string(),
+        "@target-uri" => format!(
+            "{}://{}{}",
+            scheme(request),
+            authority(request)?,
+            path_and_query(request)
+        ),
+        "@authority" => authority(request)?,
+        "@scheme" => scheme(request).to_string(),
+        "@request-target" => path_and_query(request).to_string(),
+        "@path" => match uri.path() {
+            "" => "/".to_string(),
+            path => path.to_string(),
+        },
+        "@query" => format!("?{}", uri.query().unwrap_or("")),
+        _ if name.starts_with('@') => {
+            return Err(Error::Signature(format!("unsupported component {name}")))
+        }
+        _ => header_value(request.headers(), name)?
+            .ok_or_else(|| Error::Signature(format!("signed header {name} is missing")))?,
+    };
+    Ok(value)
+}
+
+/// The scheme of the request URI. Requests received by a server only have a
+/// path, and are assumed to have come over HTTPS.
+fn scheme<B>(request: &Request<B>) -> &str {
+    request.uri().scheme_str().unwrap_or("https")
+}
+
+fn authority<B>(request: &Request<B>) -> Result<String> {
+    match request.uri().authority() {
+        Some(authority) => Ok(authority.as_str().to_lowercase()),
+        None => header_value(request.headers(), header::HOST.as_str())?
+            .map(|host| host.to_lowercase())
+            .ok_or_else(|| Error::Signature("request has no host".into())),
+    }
+}
+
+fn path_and_query<B>(request: &Request<B>) -> &str {
+    request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/")
+}
+
+///////////////////////////
+// Verification
+///////////////////////////
+/// Checks the signature of incoming requests.
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    /// How old the creation time of a signature may be.
+    pub max_age: Duration,
+    /// How far in the future the creation time of a signature may be.
+    pub clock_skew: Duration,
+    /// Components a signature must cover. `content-digest` is required as
+    /// well when the request has a body.
+    pub required_components: Vec<String>,
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Verifier {
+            max_age: Duration::from_secs(12 * 60 * 60),
+            clock_skew: Duration::from_secs(60 * 60),
+            required_components: DEFAULT_COMPONENTS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl Verifier {
+    /// Verifies that `request` carries a signature by `key` that covers the
+    /// required components, that the body matches its `Content-Digest` and
+    /// that the signature is recent.
+    pub fn verify<B: AsRef<[u8]>>(&self, request: &Request<B>, key: &PublicKeyInfo) -> Result<()> {
+        self.verify_at(request, key, SystemTime::now())
+    }
+
+    /// [Verifier::verify], taking `now` as the current time.
+    pub fn verify_at<B: AsRef<[u8]>>(
+        &self,
+        request: &Request<B>,
+        key: &PublicKeyInfo,
+        now: SystemTime,
+    ) -> Result<()> {
+        let input = SignatureInput::from_request(request)?
+            .into_iter()
+            .find(|input| input.key_id.as_deref() == Some(key.id.as_str()))
+            .ok_or_else(|| Error::Signature(format!("request is not signed with {}", key.id)))?;
+        match input.algorithm.as_deref() {
+            None | Some(ALGORITHM) => {}
+            Some(algorithm) => {
+                return Err(Error::Signature(format!(
+                    "unsupported algorithm {algorithm}"
+                )))
+            }
+        }
+
+        let covers = |name: &str| input.components.iter().any(|component| component == name);
+        if let Some(missing) = self.required_components.iter().find(|name| !covers(name)) {
+            return Err(Error::Signature(format!(
+                "signature doesn't cover {missing}"
+            )));
+        }
+
+        self.check_time(&input, now)?;
+        check_content_digest(request, covers("content-digest"))?;
+
+        let signature = signatures(request)?
+            .into_iter()
+            .find(|(label, _)| *label == input.label)
+            .map(|(_, signature)| signature)
+            .ok_or_else(|| Error::Signature(format!("no signature labelled {}", input.label)))?;
+        let base = signature_base(request, &input)?;
+        let verifier = pkcs1v15::VerifyingKey::<Sha256>::new(key.public_key()?);
+        let signature = pkcs1v15::Signature::try_from(signature.as_slice())
+            .map_err(|_| Error::Signature("malformed signature".into()))?;
+        verifier
+            .verify(base.as_bytes(), &signature)
+            .map_err(|_| Error::Signature("signature doesn't match".into()))
+    }
+
+    fn check_time(&self, input: &SignatureInput, now: SystemTime) -> Result<()> {
+        let created = input
+            .created
+            .map(unix_time)
+            .ok_or_else(|| Error::Signature("signature has no creation time".into()))??;
+        if created > now + self.clock_skew {
+            return Err(Error::Signature("signature is dated in the future".into()));
+        }
+        if now
+            .duration_since(created)
+            .is_ok_and(|age| age > self.max_age)
+        {
+            return Err(Error::Signature("signature is too old".into()));
+        }
+        if let Some(expires) = input.expires {
+            if unix_time(expires)? < now {
+                return Err(Error::Signature("signature has expired".into()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the `Content-Digest` header against the body. A request with a body
+/// must have a signed digest.
+fn check_content_digest<B: AsRef<[u8]>>(request: &Request<B>, signed: bool) -> Result<()> {
+    let body = request.body().as_ref();
+    let digest = match header_value(request.headers(), "content-digest")? {
+        Some(digest) if signed => digest,
+        Some(_) | None if body.is_empty() => return Ok(()),
+        _ => return Err(Error::Signature("body is not covered by a digest".into())),
+    };
+
+    let sha256 = dictionary(&digest)?
+        .into_iter()
+        .find(|(algorithm, _)| *algorithm == "sha-256")
+        .map(|(_, value)| value.trim_matches(':'))
+        .ok_or_else(|| Error::Signature(format!("unsupported digest: {digest}")))?;
+    if sha256 == sha256_base64(body) {
+        Ok(())
+    } else {
+        Err(Error::Signature("body doesn't match its digest".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::tests::{public_key_info, signing_key, KEY_ID};
+    use pretty_assertions::assert_eq;
+    use std::fmt;
+
+    const BODY: &str = r#"{"type":"Follow","actor":"https://example.com/users/alice","object":"https://mastodon.example/users/bob"}"#;
+
+    fn post() -> Request<Vec<u8>> {
+        Request::post("https://mastodon.example/users/bob/inbox")
+            .header(header::CONTENT_TYPE, "application/activity+json")
+            .body(BODY.as_bytes().to_vec())
+            .unwrap()
+    }
+
+    fn error<T: fmt::Debug>(result: Result<T>) -> String {
+        match result.unwrap_err() {
+            Error::Signature(message) => message,
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+
+        assert_eq!(
+            request.headers()["content-digest"],
+            format!("sha-256=:{}:", sha256_base64(BODY.as_bytes()))
+        );
+        let input = SignatureInput::from_request(&request).unwrap().remove(0);
+        assert_eq!(input.label, "sig1");
+        assert_eq!(
+            input.components,
+            vec!["@method", "@target-uri", "content-digest"]
+        );
+        assert_eq!(input.key_id.as_deref(), Some(KEY_ID));
+        assert_eq!(input.algorithm.as_deref(), Some("rsa-v1_5-sha256"));
+        assert_eq!(
+            signature_base(&request, &input).unwrap(),
+            format!(
+                "\"@method\": POST\n\"@target-uri\": https://mastodon.example/users/bob/inbox\n\"content-digest\": sha-256=:{}:\n\"@signature-params\": {}",
+                sha256_base64(BODY.as_bytes()),
+                input.serialized()
+            )
+        );
+
+        Verifier::default()
+            .verify(&request, &public_key_info())
+            .unwrap();
+    }
+
+    #[test]
+    fn verify_received_request() {
+        // The server sees the path only, and the host in a header.
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        let (mut parts, body) = request.into_parts();
+        parts.uri = "/users/bob/inbox".parse().unwrap();
+        parts
+            .headers
+            .insert(header::HOST, "mastodon.example".parse().unwrap());
+        let request = Request::from_parts(parts, body);
+        Verifier::default()
+            .verify(&request, &public_key_info())
+            .unwrap();
+    }
+
+    // Signed with `openssl dgst -sha256 -sign`, as a check against an
+    // independent implementation.
+    #[test]
+    fn verify_openssl_signature() {
+        let request = Request::post("https://mastodon.example/users/bob/inbox")
+            .header(
+                "content-digest",
+                "sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:",
+            )
+            .header("signature-input", OPENSSL_SIGNATURE_INPUT)
+            .header("signature", OPENSSL_SIGNATURE)
+            .body(b"hello".to_vec())
+            .unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(1736078430);
+        Verifier::default()
+            .verify_at(&request, &public_key_info(), now)
+            .unwrap();
+    }
+
+    const OPENSSL_SIGNATURE_INPUT: &str = concat!(
+        r#"sig1=("@method" "@target-uri" "content-digest");created=1736078400;"#,
+        r#"keyid="https://example.com/users/alice#main-key";alg="rsa-v1_5-sha256""#
+    );
+
+    const OPENSSL_SIGNATURE: &str = "sig1=:XrS+xUBtOoiSebZpAizNDHI0SydqjOtUOhhOoqqSU02Zz8Jy8zmPwAajFWV7rWMFLt0QTQO16aZqA++epG9r5dqCygGmzMMBOuu73+nLwVwcB3THwhqhmg1bxiq97Bve194L8x9IlGNvmbqh8LbjFgfdLH3jdQ7Mp7gwTabHuLIPo82Kv2FEhrRP+W4TIBsNajTeBy+g2OCGsxgVn58v6C8MSNxpglFx9HRz194shlpYR7UOzMD3MGlRxgO6CJJuK0tYY70R7EfsOyM7tqGT4FYVqbOEEqm0E8qIq1+V3lERYN64Ez+UTcOyqKKUaih7jrC5+CV8buJexUkJIh59Fg==:";
+
+    #[test]
+    fn tampered_request() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        *request.body_mut() = BODY.replace("Follow", "Block").into_bytes();
+        assert_eq!(
+            error(Verifier::default().verify(&request, &public_key_info())),
+            "body doesn't match its digest"
+        );
+
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        *request.method_mut() = Method::PUT;
+        assert_eq!(
+            error(Verifier::default().verify(&request, &public_key_info())),
+            "signature doesn't match"
+        );
+    }
+
+    #[test]
+    fn covered_components() {
+        let mut request = post();
+        sign_components(
+            &mut request,
+            &signing_key(),
+            &[
+                "@method",
+                "@authority",
+                "@path",
+                "@query",
+                "content-type",
+                "content-digest",
+                "date",
+            ],
+        )
+        .unwrap();
+        let input = SignatureInput::from_request(&request).unwrap().remove(0);
+        let base = signature_base(&request, &input).unwrap();
+        assert!(base.starts_with(
+            "\"@method\": POST\n\"@authority\": mastodon.example\n\"@path\": /users/bob/inbox\n\"@query\": ?\n\"content-type\": application/activity+json\n"
+        ));
+        assert!(request.headers().contains_key(header::DATE));
+
+        // The target URI is required by default.
+        assert_eq!(
+            error(Verifier::default().verify(&request, &public_key_info())),
+            "signature doesn't cover @target-uri"
+        );
+        let verifier = Verifier {
+            required_components: vec!["@method".into(), "@authority".into()],
+            ..Default::default()
+        };
+        verifier.verify(&request, &public_key_info()).unwrap();
+
+        let mut request = post();
+        sign_components(&mut request, &signing_key(), &["@method", "@target-uri"]).unwrap();
+        assert_eq!(
+            error(Verifier::default().verify(&request, &public_key_info())),
+            "body is not covered by a digest"
+        );
+    }
+
+    #[test]
+    fn signature_age() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        let verifier = Verifier::default();
+        let key = public_key_info();
+
+        let tomorrow = SystemTime::now() + Duration::from_secs(24 * 60 * 60);
+        assert_eq!(
+            error(verifier.verify_at(&request, &key, tomorrow)),
+            "signature is too old"
+        );
+        let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+        assert_eq!(
+            error(verifier.verify_at(&request, &key, yesterday)),
+            "signature is dated in the future"
+        );
+    }
+
+    #[test]
+    fn overflowing_times() {
+        let verifier = Verifier {
+            required_components: Vec::new(),
+            ..Verifier::default()
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        for times in [
+            "created=18446744073709551615".to_string(),
+            format!("created={now};expires=18446744073709551615"),
+        ] {
+            let mut request = post();
+            request.headers_mut().insert(
+                "signature-input",
+                format!(r#"sig1=();{times};keyid="{KEY_ID}""#)
+                    .parse()
+                    .unwrap(),
+            );
+            request
+                .headers_mut()
+                .insert("signature", "sig1=:AQID:".parse().unwrap());
+            assert_eq!(
+                error(verifier.verify(&request, &public_key_info())),
+                "invalid time: 18446744073709551615"
+            );
+        }
+    }
+
+    #[test]
+    fn other_key() {
+        let mut request = post();
+        sign(&mut request, &signing_key()).unwrap();
+        let mut key = public_key_info();
+        key.id = "https://example.com/users/mallory#main-key".into();
+        assert_eq!(
+            error(Verifier::default().verify(&request, &key)),
+            "request is not signed with https://example.com/users/mallory#main-key"
+        );
+    }
+
+    #[test]
+    fn parse_signature_input() {
+        let inputs = SignatureInput::parse(concat!(
+            r#"sig1=("@method" "@target-uri");created=1618884473;keyid="test-key";nonce="a;b", "#,
+            r#"proxy=("@authority");alg="rsa-v1_5-sha256";expires=1618884773"#
+        ))
+        .unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].components, vec!["@method", "@target-uri"]);
+        assert_eq!(inputs[0].created, Some(1618884473));
+        assert_eq!(inputs[0].key_id.as_deref(), Some("test-key"));
+        assert_eq!(
+            inputs[0].serialized(),
+            r#"("@method" "@target-uri");created=1618884473;keyid="test-key";nonce="a;b""#
+        );
+        assert_eq!(inputs[1].label, "proxy");
+        assert_eq!(inputs[1].algorithm.as_deref(), Some("rsa-v1_5-sha256"));
+        assert_eq!(inputs[1].expires, Some(1618884773));
+
+        assert!(SignatureInput::parse("sig1=\"@method\"").is_err());
+        assert!(SignatureInput::parse("sig1=(@method)").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Checks that an [ObjectStore] behaves as the crate expects, for backends
+//! to run in their tests:
+//!
+//! ```ignore
+//! #[tokio::test]
+//! async fn conforms() {
+//!     rustypub::store::conformance::run(&MyStore::open_empty()).await;
+//! }
+//! ```
+//!
+//! The checks panic on failure, like assertions, and expect an empty store.
+
+use chrono::{TimeZone, Utc};
+use serde_json::json;
+
+use super::{is_tombstone, ObjectStore, Page};
+
+const NOTE: &str = "https://conformance.example/notes/1";
+const ACTOR: &str = "https://conformance.example/users/alice";
+
+/// Runs every check against `store`.
+pub async fn run<S: ObjectStore + ?Sized>(store: &S) {
+    objects(store).await;
+    tombstones(store).await;
+    collections(store).await;
+    pages(store).await;
+}
+
+/// Objects are stored and replaced by id, and need one.
+pub async fn objects<S: ObjectStore + ?Sized>(store: &S) {
+    assert_eq!(store.get(NOTE).await.unwrap(), None, "unknown objects");
+
+    let note = json!({
+      "id": NOTE,
+      "type": "Note",
+      "attributedTo": ACTOR,
+      "content": "Hello",
+      "published": "2024-05-01T12:00:00Z",
+      "tag": [{ "type": "Hashtag", "name": "#hello" }]
+    });
+    store.put(note.clone()).await.unwrap();
+    assert_eq!(store.get(NOTE).await.unwrap(), Some(note.clone()), "put");
+
+    let mut edited = note;
+    edited["content"] = json!("Hello again");
+    store.put(edited.clone()).await.unwrap();
+    assert_eq!(store.get(NOTE).await.unwrap(), Some(edited), "replace");
+
+    assert!(
+        store.put(json!({ "type": "Note" })).await.is_err(),
+        "objects without an id"
+    );
+}
+
+/// Deleted objects are replaced by a tombstone, which keeps their type.
+pub async fn tombstones<S: ObjectStore + ?Sized>(store: &S) {
+    let id = "https://conformance.example/notes/deleted";
+    store
+        .put(json!({ "id": id, "type": "Article", "name": "Gone soon" }))
+        .await
+        .unwrap();
+    let deleted = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    store.tombstone(id, deleted).await.unwrap();
+
+    let tombstone = store.get(id).await.unwrap().expect("tombstone");
+    assert!(is_tombstone(&tombstone), "tombstone type");
+    assert_eq!(
+        tombstone,
+        json!({
+          "id": id,
+          "type": "Tombstone",
+          "formerType": "Article",
+          "deleted": "2024-05-01T12:00:00Z"
+        }),
+        "tombstone"
+    );
+
+    // Deleting again, or something never seen, leaves a tombstone too.
+    store.tombstone(id, deleted).await.unwrap();
+    assert_eq!(store.get(id).await.unwrap(), Some(tombstone));
+    let unknown = "https://conformance.example/notes/unknown";
+    store.tombstone(unknown, deleted).await.unwrap();
+    assert!(is_tombstone(&store.get(unknown).await.unwrap().unwrap()));
+}
+
+/// Collections hold each item once, and can be changed independently.
+pub async fn collections<S: ObjectStore + ?Sized>(store: &S) {
+    let followers = format!("{ACTOR}/followers");
+    let following = format!("{ACTOR}/following");
+    let bob = "https://conformance.example/users/bob";
+
+    assert!(!store.contains(&followers, bob).await.unwrap());
+    assert!(store.append(&followers, bob).await.unwrap(), "append");
+    assert!(
+        !store.append(&followers, bob).await.unwrap(),
+        "append twice"
+    );
+    assert!(store.contains(&followers, bob).await.unwrap());
+    assert!(!store.contains(&following, bob).await.unwrap(), "separate");
+
+    assert!(store.remove(&followers, bob).await.unwrap(), "remove");
+    assert!(
+        !store.remove(&followers, bob).await.unwrap(),
+        "remove twice"
+    );
+    assert!(!store.contains(&followers, bob).await.unwrap());
+    assert!(!store.remove(&following, bob).await.unwrap(), "unknown");
+
+    // Items needn't be stored objects.
+    assert!(store.append(&following, NOTE).await.unwrap());
+    assert!(store.contains(&following, NOTE).await.unwrap());
+}
+
+/// Collections are paged newest first.
+pub async fn pages<S: ObjectStore + ?Sized>(store: &S) {
+    let outbox = format!("{ACTOR}/outbox");
+    let create = |n: usize| format!("https://conformance.example/creates/{n}");
+    assert_eq!(
+        store.page(&outbox, 0, 10).await.unwrap(),
+        Page::default(),
+        "empty collection"
+    );
+
+    for n in 1..=5 {
+        store.append(&outbox, &create(n)).await.unwrap();
+    }
+    // Appending again doesn't move an item.
+    store.append(&outbox, &create(1)).await.unwrap();
+
+    let page = |offset, limit| store.page(&outbox, offset, limit);
+    assert_eq!(
+        page(0, 2).await.unwrap(),
+        Page {
+            items: vec![create(5), create(4)],
+            total_items: 5,
+        },
+        "first page"
+    );
+    assert_eq!(page(2, 2).await.unwrap().items, vec![create(3), create(2)]);
+    assert_eq!(
+        page(4, 2).await.unwrap().items,
+        vec![create(1)],
+        "last page"
+    );
+    assert_eq!(page(5, 2).await.unwrap().items, Vec::<String>::new());
+    assert_eq!(page(0, 0).await.unwrap().total_items, 5, "count only");
+
+    store.remove(&outbox, &create(4)).await.unwrap();
+    store.append(&outbox, &create(6)).await.unwrap();
+    assert_eq!(
+        page(0, 10).await.unwrap(),
+        Page {
+            items: vec![create(6), create(5), create(3), create(2), create(1)],
+            total_items: 5,
+        },
+        "after changes"
+    );
+
+    // Removing and appending again makes an item the newest.
+    store.remove(&outbox, &create(1)).await.unwrap();
+    store.append(&outbox, &create(1)).await.unwrap();
+    assert_eq!(page(0, 1).await.unwrap().items, vec![create(1)]);
+}
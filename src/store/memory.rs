@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use super::{object_id, tombstone, ObjectStore, Page};
+use crate::Result;
+
+/// An [ObjectStore] in memory: everything is lost when the process ends.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    objects: Mutex<HashMap<String, Value>>,
+    collections: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    fn objects(&self) -> MutexGuard<'_, HashMap<String, Value>> {
+        self.objects.lock().expect("store lock poisoned")
+    }
+
+    fn collections(&self) -> MutexGuard<'_, HashMap<String, Vec<String>>> {
+        self.collections.lock().expect("store lock poisoned")
+    }
+}
+
+#[async_trait]
+impl ObjectStore for MemoryStore {
+    async fn get(&self, id: &str) -> Result<Option<Value>> {
+        Ok(self.objects().get(id).cloned())
+    }
+
+    async fn put(&self, object: Value) -> Result<()> {
+        let id = object_id(&object)?.to_string();
+        self.objects().insert(id, object);
+        Ok(())
+    }
+
+    async fn tombstone(&self, id: &str, deleted: DateTime<Utc>) -> Result<()> {
+        let mut objects = self.objects();
+        let tombstone = tombstone(id, objects.get(id), deleted)?;
+        objects.insert(id.to_string(), tombstone);
+        Ok(())
+    }
+
+    async fn append(&self, collection: &str, item: &str) -> Result<bool> {
+        let mut collections = self.collections();
+        let items = collections.entry(collection.to_string()).or_default();
+        if items.iter().any(|existing| existing == item) {
+            return Ok(false);
+        }
+        items.push(item.to_string());
+        Ok(true)
+    }
+
+    async fn remove(&self, collection: &str, item: &str) -> Result<bool> {
+        let mut collections = self.collections();
+        let Some(items) = collections.get_mut(collection) else {
+            return Ok(false);
+        };
+        let len = items.len();
+        items.retain(|existing| existing != item);
+        Ok(items.len() != len)
+    }
+
+    async fn contains(&self, collection: &str, item: &str) -> Result<bool> {
+        Ok(self
+            .collections()
+            .get(collection)
+            .is_some_and(|items| items.iter().any(|existing| existing == item)))
+    }
+
+    async fn page(&self, collection: &str, offset: usize, limit: usize) -> Result<Page> {
+        let collections = self.collections();
+        let Some(items) = collections.get(collection) else {
+            return Ok(Page::default());
+        };
+        Ok(Page {
+            items: items
+                .iter()
+                .rev()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+            total_items: items.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::conformance;
+
+    #[tokio::test]
+    async fn conforms() {
+        conformance::run(&MemoryStore::new()).await;
+    }
+}
@@ -0,0 +1,307 @@
+//! Storage of objects, activities and the collections they belong to.
+//!
+//! [ObjectStore] is what the rest of the crate persists state through, e.g.
+//! the [side effects](crate::side_effects) of received activities. Objects
+//! are stored as JSON, by id; collections are ordered lists of ids, paged
+//! newest first. [MemoryStore] keeps everything in memory, `SqliteStore` (with
+//! the `sqlite` feature) in an SQLite database, and [conformance] checks that
+//! a backend behaves like them.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::core::collection::{OrderedCollection, OrderedCollectionPage};
+use crate::core::object::{Object, ObjectOrLink, Tombstone};
+use crate::core::{Context, Document};
+use crate::{Error, Result};
+
+pub mod conformance;
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use memory::MemoryStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// The `type` of what deleted objects are replaced with.
+pub const TOMBSTONE: &str = Tombstone::TYPE;
+
+/// Items of a collection, as returned by [ObjectStore::page].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Page {
+    /// The ids of the items, newest first.
+    pub items: Vec<String>,
+    /// The number of items in the whole collection.
+    pub total_items: usize,
+}
+
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// The object with the id `id`, which is a [TOMBSTONE] if it was deleted.
+    async fn get(&self, id: &str) -> Result<Option<Value>>;
+
+    /// Stores an object under its `id`, replacing any object with that id.
+    async fn put(&self, object: Value) -> Result<()>;
+
+    /// Replaces the object `id` with a [TOMBSTONE], as of `deleted`, so that
+    /// it's known to be gone. The tombstone keeps the object's type as its
+    /// `formerType`, and deleting it again leaves it as it is.
+    async fn tombstone(&self, id: &str, deleted: DateTime<Utc>) -> Result<()>;
+
+    /// Adds an item to the end of a collection, returning whether it wasn't
+    /// in it already.
+    async fn append(&self, collection: &str, item: &str) -> Result<bool>;
+
+    /// Removes an item from a collection, returning whether it was in it.
+    async fn remove(&self, collection: &str, item: &str) -> Result<bool>;
+
+    async fn contains(&self, collection: &str, item: &str) -> Result<bool>;
+
+    /// Up to `limit` items of a collection, newest first, skipping the
+    /// `offset` newest. Collections nothing was appended to are empty.
+    async fn page(&self, collection: &str, offset: usize, limit: usize) -> Result<Page>;
+
+    /// The object with the id `id` as a document, in the Activity Streams
+    /// [Context] if it was stored without one.
+    async fn get_document<T>(&self, id: &str) -> Result<Option<Document<T>>>
+    where
+        Self: Sized,
+        T: DeserializeOwned + Serialize,
+    {
+        let Some(mut object) = self.get(id).await? else {
+            return Ok(None);
+        };
+        if let Value::Object(properties) = &mut object {
+            if !properties.contains_key("@context") {
+                properties.insert("@context".into(), serde_json::to_value(Context::new())?);
+            }
+        }
+        Ok(Some(serde_json::from_value(object)?))
+    }
+
+    /// Stores a document, with its `@context`, under the id of its object.
+    async fn put_document<T>(&self, document: &Document<T>) -> Result<()>
+    where
+        Self: Sized,
+        T: DeserializeOwned + Serialize + Sync,
+    {
+        self.put(serde_json::to_value(document)?).await
+    }
+}
+
+/// The tombstone of an object. Tombstones stay as they are.
+pub fn tombstone(id: &str, former: Option<&Value>, deleted: DateTime<Utc>) -> Result<Value> {
+    if let Some(former) = former.filter(|former| is_tombstone(former)) {
+        return Ok(former.clone());
+    }
+    let former_type = former
+        .and_then(|former| former.get("type"))
+        .and_then(|former_type| serde_json::from_value(former_type.clone()).ok());
+    Ok(serde_json::to_value(Tombstone::new(
+        id.into(),
+        former_type,
+        deleted,
+    ))?)
+}
+
+/// Whether a stored object is a tombstone.
+pub fn is_tombstone(object: &Value) -> bool {
+    object.get("type").and_then(Value::as_str) == Some(TOMBSTONE)
+}
+
+/// The id of an object to be stored.
+pub(crate) fn object_id(object: &Value) -> Result<&str> {
+    object
+        .get("id")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::Validation("can't store an object without an id".into()))
+}
+
+///////////////////////////
+// Paging
+///////////////////////////
+/// The id of page `page` of a collection, counting from 1.
+pub fn page_id(collection: &str, page: usize) -> String {
+    format!("{collection}?page={page}")
+}
+
+/// A collection as served at its id: its size and a link to its first page.
+pub async fn ordered_collection<S: ObjectStore + ?Sized>(
+    store: &S,
+    collection: &str,
+) -> Result<Document<OrderedCollection<String>>> {
+    let total_items = store.page(collection, 0, 0).await?.total_items;
+    Ok(Document::new(
+        Context::new(),
+        OrderedCollection {
+            base: Object {
+                object_type: Some("OrderedCollection".into()),
+                id: Some(collection.to_string()),
+                ..Object::default()
+            },
+            total_items: Some(total_items),
+            ordered_items: Vec::new(),
+            first: (total_items > 0).then(|| ObjectOrLink::iri(page_id(collection, 1))),
+            last: None,
+            current: None,
+        },
+    ))
+}
+
+/// Page `page`, counting from 1, of a collection in pages of `size` items.
+pub async fn ordered_page<S: ObjectStore + ?Sized>(
+    store: &S,
+    collection: &str,
+    page: usize,
+    size: usize,
+) -> Result<Document<OrderedCollectionPage<String>>> {
+    // Pages past the end are empty, but not pages that can't even be counted.
+    let offset = page
+        .checked_sub(1)
+        .and_then(|before| before.checked_mul(size))
+        .filter(|_| size > 0)
+        .ok_or_else(|| Error::Validation(format!("there's no page {page} of {size} items")))?;
+    let Page { items, total_items } = store.page(collection, offset, size).await?;
+    Ok(Document::new(
+        Context::new(),
+        OrderedCollectionPage {
+            base: OrderedCollection {
+                base: Object {
+                    object_type: Some("OrderedCollectionPage".into()),
+                    id: Some(page_id(collection, page)),
+                    ..Object::default()
+                },
+                total_items: Some(total_items),
+                ordered_items: items,
+                first: None,
+                last: None,
+                current: None,
+            },
+            part_of: collection.to_string(),
+            next: (offset.saturating_add(size) < total_items)
+                .then(|| page_id(collection, page + 1)),
+            prev: (page > 1).then(|| page_id(collection, page - 1)),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn tombstones() {
+        let deleted = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let note = json!({ "id": "https://example.social/notes/1", "type": "Note" });
+        let tombstone = tombstone("https://example.social/notes/1", Some(&note), deleted).unwrap();
+        assert!(is_tombstone(&tombstone));
+        assert_eq!(
+            tombstone,
+            json!({
+              "id": "https://example.social/notes/1",
+              "type": "Tombstone",
+              "formerType": "Note",
+              "deleted": "2024-05-01T12:00:00Z"
+            })
+        );
+        assert!(!is_tombstone(&note));
+    }
+
+    #[tokio::test]
+    async fn pages() {
+        let store = MemoryStore::new();
+        let outbox = "https://example.social/users/alice/outbox";
+        for n in 1..=5 {
+            store
+                .append(outbox, &format!("https://example.social/creates/{n}"))
+                .await
+                .unwrap();
+        }
+
+        let collection = ordered_collection(&store, outbox).await.unwrap();
+        assert_eq!(
+            serde_json::to_value(&collection).unwrap(),
+            json!({
+              "@context": { "@vocab": "https://www.w3.org/ns/activitystreams" },
+              "id": outbox,
+              "type": "OrderedCollection",
+              "totalItems": 5,
+              "first": format!("{outbox}?page=1")
+            })
+        );
+
+        let first = ordered_page(&store, outbox, 1, 2).await.unwrap();
+        assert_eq!(
+            serde_json::to_value(&first).unwrap(),
+            json!({
+              "@context": { "@vocab": "https://www.w3.org/ns/activitystreams" },
+              "id": format!("{outbox}?page=1"),
+              "type": "OrderedCollectionPage",
+              "totalItems": 5,
+              "orderedItems": [
+                "https://example.social/creates/5",
+                "https://example.social/creates/4"
+              ],
+              "partOf": outbox,
+              "next": format!("{outbox}?page=2")
+            })
+        );
+
+        let last = ordered_page(&store, outbox, 3, 2).await.unwrap().object;
+        assert_eq!(
+            last.base.ordered_items,
+            vec!["https://example.social/creates/1".to_string()]
+        );
+        assert_eq!(last.next, None);
+        assert_eq!(last.prev, Some(format!("{outbox}?page=2")));
+
+        assert!(ordered_page(&store, outbox, 0, 2).await.is_err());
+        assert!(ordered_page(&store, outbox, 1, 0).await.is_err());
+        assert!(ordered_page(&store, outbox, usize::MAX, 2).await.is_err());
+        let past_the_end = ordered_page(&store, outbox, 1000, 2).await.unwrap().object;
+        assert!(past_the_end.base.ordered_items.is_empty());
+        assert_eq!(past_the_end.next, None);
+    }
+
+    #[tokio::test]
+    async fn documents() {
+        use crate::core::object::ObjectBuilder;
+
+        let store = MemoryStore::new();
+        let note = ObjectBuilder::new()
+            .id(Some("https://example.social/notes/1".into()))
+            .object_type(Some("Note".into()))
+            .content(Some("Hello".into()))
+            .build()
+            .unwrap();
+        store
+            .put_document(&Document::new(Context::new(), note.clone()))
+            .await
+            .unwrap();
+        let stored: Document<Object> = store
+            .get_document("https://example.social/notes/1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.object, note);
+
+        // Objects stored without a context are in the default one.
+        store
+            .put(json!({ "id": "https://example.social/notes/2", "type": "Note" }))
+            .await
+            .unwrap();
+        let stored: Document<Object> = store
+            .get_document("https://example.social/notes/2")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(stored.context.is_activitystreams());
+    }
+}
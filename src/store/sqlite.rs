@@ -0,0 +1,442 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use super::{object_id, tombstone, ObjectStore, Page};
+use crate::{Error, Result};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS objects (
+    id TEXT PRIMARY KEY NOT NULL,
+    type TEXT,
+    attributed_to TEXT,
+    in_reply_to TEXT,
+    published TEXT,
+    object TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS objects_type ON objects (type);
+CREATE INDEX IF NOT EXISTS objects_attributed_to ON objects (attributed_to, published);
+CREATE INDEX IF NOT EXISTS objects_in_reply_to ON objects (in_reply_to, published);
+CREATE INDEX IF NOT EXISTS objects_published ON objects (published);
+
+CREATE TABLE IF NOT EXISTS collection_items (
+    position INTEGER PRIMARY KEY AUTOINCREMENT,
+    collection TEXT NOT NULL,
+    item TEXT NOT NULL,
+    UNIQUE (collection, item)
+);
+CREATE INDEX IF NOT EXISTS collection_items_order ON collection_items (collection, position);
+";
+
+/// An [ObjectStore] in an SQLite database, e.g. a local file.
+///
+/// Objects are stored as JSON, with their `type`, `attributedTo`,
+/// `inReplyTo` and `published` in indexed columns for [SqliteStore::by_type],
+/// [SqliteStore::attributed_to] and [SqliteStore::replies]; `published` is
+/// normalized to UTC so that objects sort by when they were published,
+/// whatever their time zone. Collections keep the order items were appended
+/// in.
+///
+/// Queries block, so they run one at a time on Tokio's blocking threads, with
+/// [spawn_blocking](tokio::task::spawn_blocking): the store must be used
+/// within a Tokio runtime. Clones share the connection.
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it and its tables as needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        SqliteStore::with_connection(Connection::open(path)?)
+    }
+
+    /// A database in memory, lost when the store and its clones are dropped.
+    pub fn open_in_memory() -> Result<Self> {
+        SqliteStore::with_connection(Connection::open_in_memory()?)
+    }
+
+    pub fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs `query` on a blocking thread, with the connection to itself.
+    async fn run<T, F>(&self, query: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = Arc::clone(&self.connection);
+        tokio::task::spawn_blocking(move || {
+            query(&mut connection.lock().expect("store lock poisoned"))
+        })
+        .await
+        .map_err(|err| Error::Storage(format!("query failed: {err}")))?
+    }
+
+    /// Objects of a type, e.g. `Note`, newest first.
+    pub async fn by_type(&self, object_type: &str, limit: usize) -> Result<Vec<Value>> {
+        self.select("type", object_type, limit).await
+    }
+
+    /// Objects attributed to an actor, newest first.
+    pub async fn attributed_to(&self, actor: &str, limit: usize) -> Result<Vec<Value>> {
+        self.select("attributed_to", actor, limit).await
+    }
+
+    /// Replies to an object, newest first.
+    pub async fn replies(&self, id: &str, limit: usize) -> Result<Vec<Value>> {
+        self.select("in_reply_to", id, limit).await
+    }
+
+    /// Objects whose indexed `column` is `value`. Only called with the names
+    /// of the columns above.
+    async fn select(&self, column: &'static str, value: &str, limit: usize) -> Result<Vec<Value>> {
+        let value = value.to_string();
+        self.run(move |connection| {
+            let mut statement = connection.prepare_cached(&format!(
+                "SELECT object FROM objects WHERE {column} = ?1
+                 ORDER BY published DESC, rowid DESC LIMIT ?2"
+            ))?;
+            let objects = statement
+                .query_map(params![value, limit as i64], |row| row.get::<_, String>(0))?
+                .map(|object| Ok(serde_json::from_str(&object?)?))
+                .collect();
+            objects
+        })
+        .await
+    }
+}
+
+/// The id in a property of an object, e.g. `attributedTo`: a string, an
+/// object with an id, or the first of several.
+fn property_id<'a>(object: &'a Value, name: &str) -> Option<&'a str> {
+    fn id(value: &Value) -> Option<&str> {
+        match value {
+            Value::String(id) => Some(id),
+            Value::Array(values) => values.first().and_then(id),
+            value => value.get("id").and_then(Value::as_str),
+        }
+    }
+    object.get(name).and_then(id)
+}
+
+/// The `published` time of an object in UTC, with a fixed number of digits,
+/// so that sorting the strings sorts the times.
+fn published(object: &Value) -> Option<String> {
+    let published = object.get("published").and_then(Value::as_str)?;
+    let published = DateTime::parse_from_rfc3339(published).ok()?;
+    Some(
+        published
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+    )
+}
+
+fn insert(connection: &Connection, id: &str, object: &Value) -> Result<()> {
+    connection
+        .prepare_cached(
+            "INSERT OR REPLACE INTO objects
+             (id, type, attributed_to, in_reply_to, published, object)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?
+        .execute(params![
+            id,
+            object.get("type").and_then(Value::as_str),
+            property_id(object, "attributedTo"),
+            property_id(object, "inReplyTo"),
+            published(object),
+            object.to_string(),
+        ])?;
+    Ok(())
+}
+
+fn get(connection: &Connection, id: &str) -> Result<Option<Value>> {
+    let object = connection
+        .prepare_cached("SELECT object FROM objects WHERE id = ?1")?
+        .query_row([id], |row| row.get::<_, String>(0))
+        .optional()?;
+    match object {
+        Some(object) => Ok(Some(serde_json::from_str(&object)?)),
+        None => Ok(None),
+    }
+}
+
+#[async_trait]
+impl ObjectStore for SqliteStore {
+    async fn get(&self, id: &str) -> Result<Option<Value>> {
+        let id = id.to_string();
+        self.run(move |connection| get(connection, &id)).await
+    }
+
+    async fn put(&self, object: Value) -> Result<()> {
+        self.run(move |connection| insert(connection, object_id(&object)?, &object))
+            .await
+    }
+
+    async fn tombstone(&self, id: &str, deleted: DateTime<Utc>) -> Result<()> {
+        let id = id.to_string();
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            let former = get(&transaction, &id)?;
+            insert(
+                &transaction,
+                &id,
+                &tombstone(&id, former.as_ref(), deleted)?,
+            )?;
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn append(&self, collection: &str, item: &str) -> Result<bool> {
+        let (collection, item) = (collection.to_string(), item.to_string());
+        self.run(move |connection| {
+            let appended = connection
+                .prepare_cached(
+                    "INSERT OR IGNORE INTO collection_items (collection, item) VALUES (?1, ?2)",
+                )?
+                .execute([collection, item])?;
+            Ok(appended > 0)
+        })
+        .await
+    }
+
+    async fn remove(&self, collection: &str, item: &str) -> Result<bool> {
+        let (collection, item) = (collection.to_string(), item.to_string());
+        self.run(move |connection| {
+            let removed = connection
+                .prepare_cached("DELETE FROM collection_items WHERE collection = ?1 AND item = ?2")?
+                .execute([collection, item])?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    async fn contains(&self, collection: &str, item: &str) -> Result<bool> {
+        let (collection, item) = (collection.to_string(), item.to_string());
+        self.run(move |connection| {
+            let contains = connection
+                .prepare_cached(
+                    "SELECT EXISTS (SELECT 1 FROM collection_items WHERE collection = ?1 AND item = ?2)",
+                )?
+                .query_row([collection, item], |row| row.get(0))?;
+            Ok(contains)
+        })
+        .await
+    }
+
+    async fn page(&self, collection: &str, offset: usize, limit: usize) -> Result<Page> {
+        let collection = collection.to_string();
+        self.run(move |connection| {
+            let total_items: i64 = connection
+                .prepare_cached("SELECT COUNT(*) FROM collection_items WHERE collection = ?1")?
+                .query_row([&collection], |row| row.get(0))?;
+            let items = connection
+                .prepare_cached(
+                    "SELECT item FROM collection_items WHERE collection = ?1
+                     ORDER BY position DESC LIMIT ?2 OFFSET ?3",
+                )?
+                .query_map(params![collection, limit as i64, offset as i64], |row| {
+                    row.get(0)
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(Page {
+                items,
+                total_items: total_items as usize,
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::conformance;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    const ALICE: &str = "https://example.social/users/alice";
+
+    fn note(n: usize, published: &str, in_reply_to: Option<&str>) -> Value {
+        let mut note = json!({
+          "id": format!("https://example.social/notes/{n}"),
+          "type": "Note",
+          "attributedTo": [{ "type": "Person", "id": ALICE }],
+          "published": published
+        });
+        if let Some(in_reply_to) = in_reply_to {
+            note["inReplyTo"] = json!(in_reply_to);
+        }
+        note
+    }
+
+    #[tokio::test]
+    async fn conforms() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqliteStore::open(dir.path().join("store.db")).unwrap();
+        conformance::run(&store).await;
+    }
+
+    #[tokio::test]
+    async fn conforms_in_memory() {
+        conformance::run(&SqliteStore::open_in_memory().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.db");
+        let outbox = format!("{ALICE}/outbox");
+        {
+            let store = SqliteStore::open(&path).unwrap();
+            store
+                .put(note(1, "2024-05-01T12:00:00Z", None))
+                .await
+                .unwrap();
+            store
+                .put(note(2, "2024-05-02T12:00:00Z", None))
+                .await
+                .unwrap();
+            for collection in ["inbox", "outbox", "followers", "following", "liked"] {
+                store
+                    .append(&format!("{ALICE}/{collection}"), "https://example.social/x")
+                    .await
+                    .unwrap();
+            }
+            store
+                .append(&outbox, "https://example.social/notes/1")
+                .await
+                .unwrap();
+            store
+                .tombstone("https://example.social/notes/2", Utc::now())
+                .await
+                .unwrap();
+        }
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(
+            store.get("https://example.social/notes/1").await.unwrap(),
+            Some(note(1, "2024-05-01T12:00:00Z", None))
+        );
+        let tombstone = store
+            .get("https://example.social/notes/2")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tombstone["formerType"], "Note");
+        assert_eq!(
+            store.page(&outbox, 0, 10).await.unwrap().items,
+            vec![
+                "https://example.social/notes/1".to_string(),
+                "https://example.social/x".to_string()
+            ]
+        );
+        assert!(store
+            .contains(&format!("{ALICE}/liked"), "https://example.social/x")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn queries() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let first = "https://example.social/notes/1";
+        store
+            .put(note(1, "2024-05-01T12:00:00Z", None))
+            .await
+            .unwrap();
+        store
+            .put(note(2, "2024-05-03T12:00:00Z", Some(first)))
+            .await
+            .unwrap();
+        store
+            .put(note(3, "2024-05-02T12:00:00Z", Some(first)))
+            .await
+            .unwrap();
+        store
+            .put(json!({ "id": ALICE, "type": "Person" }))
+            .await
+            .unwrap();
+
+        let ids = |objects: Vec<Value>| -> Vec<String> {
+            objects
+                .iter()
+                .map(|object| object["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let note_id = |n| format!("https://example.social/notes/{n}");
+        assert_eq!(
+            ids(store.attributed_to(ALICE, 10).await.unwrap()),
+            vec![note_id(2), note_id(3), note_id(1)]
+        );
+        assert_eq!(
+            ids(store.attributed_to(ALICE, 1).await.unwrap()),
+            vec![note_id(2)]
+        );
+        assert_eq!(
+            ids(store.replies(first, 10).await.unwrap()),
+            vec![note_id(2), note_id(3)]
+        );
+        assert_eq!(
+            ids(store.by_type("Person", 10).await.unwrap()),
+            vec![ALICE.to_string()]
+        );
+
+        // Deleted objects are no longer attributed, or replies.
+        store.tombstone(&note_id(2), Utc::now()).await.unwrap();
+        assert_eq!(
+            ids(store.replies(first, 10).await.unwrap()),
+            vec![note_id(3)]
+        );
+        assert_eq!(
+            ids(store.by_type("Tombstone", 10).await.unwrap()),
+            vec![note_id(2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn orders_by_instant() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let note_id = |n| format!("https://example.social/notes/{n}");
+        let ids = |objects: Vec<Value>| -> Vec<String> {
+            objects
+                .iter()
+                .map(|object| object["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        // Times in other zones or with fractions of a second sort by instant:
+        // 4 was published at 10:00 UTC, 6 at 11:00 and 5 just after.
+        store
+            .put(note(4, "2024-05-04T12:00:00+02:00", None))
+            .await
+            .unwrap();
+        store
+            .put(note(5, "2024-05-04T11:00:00.123Z", None))
+            .await
+            .unwrap();
+        store
+            .put(note(6, "2024-05-04T11:00:00Z", None))
+            .await
+            .unwrap();
+        assert_eq!(
+            ids(store.attributed_to(ALICE, 10).await.unwrap()),
+            vec![note_id(5), note_id(6), note_id(4)]
+        );
+        // Stored objects keep their time as it was.
+        assert_eq!(
+            store.get(&note_id(4)).await.unwrap().unwrap()["published"],
+            "2024-05-04T12:00:00+02:00"
+        );
+    }
+}
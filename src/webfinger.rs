@@ -0,0 +1,470 @@
+//! [WebFinger](https://www.rfc-editor.org/rfc/rfc7033), which the fediverse
+//! uses to find the actor behind a handle like `@alice@example.social`.
+//!
+//! The handle is read as an [Acct] URI. [resolve] asks the account's server
+//! for its [Jrd] and returns the id of the actor it links to; [Jrd::for_actor]
+//! is the answer a server gives about its own actors.
+
+use std::fmt;
+use std::str::FromStr;
+
+use http::{header, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::client::HttpClient;
+use crate::core::actor::Actor;
+use crate::{Error, Result};
+
+/// The path WebFinger requests are sent to.
+pub const WEBFINGER_PATH: &str = "/.well-known/webfinger";
+
+/// The media type of a [Jrd].
+pub const JRD_MEDIA_TYPE: &str = "application/jrd+json";
+
+/// The media type of an ActivityPub actor.
+pub const ACTIVITY_MEDIA_TYPE: &str = "application/activity+json";
+
+/// The relation of the link to the actor the account belongs to.
+pub const REL_SELF: &str = "self";
+
+/// The relation of the link to the account's web page.
+pub const REL_PROFILE_PAGE: &str = "http://webfinger.net/rel/profile-page";
+
+/// The relation of the link template to follow an account remotely.
+pub const REL_SUBSCRIBE: &str = "http://ostatus.org/schema/1.0/subscribe";
+
+///////////////////////////
+// Acct
+///////////////////////////
+/// An `acct:` URI, as in [RFC 7565](https://www.rfc-editor.org/rfc/rfc7565):
+/// an account `user` on the server `host`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Acct {
+    pub user: String,
+    pub host: String,
+}
+
+impl Acct {
+    pub fn new<U: Into<String>, H: Into<String>>(user: U, host: H) -> Self {
+        Acct {
+            user: user.into(),
+            host: host.into(),
+        }
+    }
+
+    /// The account as a handle, e.g. `@alice@example.social`.
+    pub fn handle(&self) -> String {
+        format!("@{}@{}", self.user, self.host)
+    }
+}
+
+impl FromStr for Acct {
+    type Err = Error;
+
+    /// Reads `acct:alice@example.social`, or a handle like
+    /// `@alice@example.social` or `alice@example.social`.
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || Error::Validation(format!("invalid account: {value}"));
+        let account = value.trim();
+        let account = account
+            .strip_prefix("acct:")
+            .or_else(|| account.strip_prefix('@'))
+            .unwrap_or(account);
+        let (user, host) = account.rsplit_once('@').ok_or_else(invalid)?;
+        let valid_host = !host.is_empty()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'));
+        if user.is_empty() || user.contains(['/', '?', '#', ' ']) || !valid_host {
+            return Err(invalid());
+        }
+        Ok(Acct::new(user, host.to_ascii_lowercase()))
+    }
+}
+
+impl fmt::Display for Acct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "acct:{}@{}", self.user, self.host)
+    }
+}
+
+///////////////////////////
+// JRD
+///////////////////////////
+/// A JSON Resource Descriptor: what a server knows about a resource.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Jrd {
+    pub subject: String,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<JrdLink>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JrdLink {
+    pub rel: String,
+
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+
+    /// A URI template, e.g. of [REL_SUBSCRIBE] links, with a `{uri}`
+    /// variable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+impl JrdLink {
+    pub fn new<R: Into<String>>(rel: R) -> Self {
+        JrdLink {
+            rel: rel.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Jrd {
+    /// The id of the actor the resource belongs to: the `href` of its `self`
+    /// link with an ActivityPub media type.
+    pub fn actor_id(&self) -> Option<&str> {
+        self.links
+            .iter()
+            .filter(|link| link.rel == REL_SELF)
+            .find(|link| {
+                link.media_type
+                    .as_deref()
+                    .is_some_and(is_activity_media_type)
+            })
+            .and_then(|link| link.href.as_deref())
+    }
+
+    /// The JRD of a local actor known as `acct`, linking to the actor and, if
+    /// it has a `url`, to its web page.
+    pub fn for_actor(actor: &Actor, acct: &Acct) -> Result<Self> {
+        let id = actor
+            .base
+            .id
+            .clone()
+            .ok_or_else(|| Error::Validation("actor has no id".into()))?;
+        let page = actor
+            .base
+            .url
+            .as_ref()
+            .and_then(|url| url.first())
+            .and_then(|url| url.id())
+            .map(str::to_string);
+
+        let mut aliases = vec![id.clone()];
+        let mut links = vec![JrdLink {
+            media_type: Some(ACTIVITY_MEDIA_TYPE.into()),
+            href: Some(id),
+            ..JrdLink::new(REL_SELF)
+        }];
+        if let Some(page) = page {
+            aliases.push(page.clone());
+            links.push(JrdLink {
+                media_type: Some("text/html".into()),
+                href: Some(page),
+                ..JrdLink::new(REL_PROFILE_PAGE)
+            });
+        }
+        Ok(Jrd {
+            subject: acct.to_string(),
+            aliases,
+            links,
+        })
+    }
+}
+
+/// Whether `media_type` is one an ActivityPub actor is served as.
+fn is_activity_media_type(media_type: &str) -> bool {
+    media_type == ACTIVITY_MEDIA_TYPE
+        || (media_type.starts_with("application/ld+json")
+            && media_type.contains("https://www.w3.org/ns/activitystreams"))
+}
+
+///////////////////////////
+// Client
+///////////////////////////
+/// The WebFinger request for `acct`, sent to its host over HTTPS.
+pub fn request(acct: &Acct) -> Result<Request<Vec<u8>>> {
+    let uri = format!(
+        "https://{}{WEBFINGER_PATH}?resource={}",
+        acct.host,
+        percent_encode(&acct.to_string())
+    );
+    Request::get(uri)
+        .header(header::ACCEPT, JRD_MEDIA_TYPE)
+        .body(Vec::new())
+        .map_err(|err| Error::Validation(format!("invalid WebFinger request: {err}")))
+}
+
+/// Looks up `acct` on its server and returns the id of its actor.
+pub async fn resolve<C: HttpClient + ?Sized>(client: &C, acct: &Acct) -> Result<String> {
+    let request = request(acct)?;
+    let uri = request.uri().to_string();
+    let response = client.execute(request).await?;
+    if !response.status().is_success() {
+        return Err(Error::Http {
+            uri,
+            status: response.status(),
+        });
+    }
+    let jrd: Jrd = serde_json::from_slice(response.body())?;
+    jrd.actor_id()
+        .map(str::to_string)
+        .ok_or_else(|| Error::Validation(format!("{acct} has no ActivityPub actor")))
+}
+
+///////////////////////////
+// Server
+///////////////////////////
+/// The account a WebFinger request asks about, from its `resource` query
+/// parameter.
+pub fn requested_resource<B>(request: &Request<B>) -> Result<Acct> {
+    request
+        .uri()
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == "resource")
+        .map(|(_, value)| percent_decode(value))
+        .transpose()?
+        .ok_or_else(|| Error::Validation("WebFinger request has no resource".into()))?
+        .parse()
+}
+
+/// The response to a WebFinger request: `jrd`, or 404 if there's none.
+pub fn response(jrd: Option<&Jrd>) -> Result<Response<Vec<u8>>> {
+    let builder = Response::builder();
+    let response = match jrd {
+        Some(jrd) => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, JRD_MEDIA_TYPE)
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .body(serde_json::to_vec(jrd)?),
+        None => builder.status(StatusCode::NOT_FOUND).body(Vec::new()),
+    };
+    response.map_err(|err| Error::Validation(format!("invalid WebFinger response: {err}")))
+}
+
+///////////////////////////
+// Helpers
+///////////////////////////
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(value: &str) -> Result<String> {
+    let invalid = || Error::Validation(format!("invalid query parameter: {value}"));
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'%' => {
+                let hex = tail.get(..2).ok_or_else(invalid)?;
+                let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+                bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+                rest = &tail[2..];
+            }
+            b'+' => {
+                bytes.push(b' ');
+                rest = tail;
+            }
+            byte => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::actor::ActorBuilder;
+    use async_trait::async_trait;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn mastodon_jrd() -> serde_json::Value {
+        json!({
+          "subject": "acct:alice@example.social",
+          "aliases": [
+            "https://example.social/@alice",
+            "https://example.social/users/alice"
+          ],
+          "links": [
+            {
+              "rel": "http://webfinger.net/rel/profile-page",
+              "type": "text/html",
+              "href": "https://example.social/@alice"
+            },
+            {
+              "rel": "self",
+              "type": "application/activity+json",
+              "href": "https://example.social/users/alice"
+            },
+            {
+              "rel": "http://ostatus.org/schema/1.0/subscribe",
+              "template": "https://example.social/authorize_interaction?uri={uri}"
+            }
+          ]
+        })
+    }
+
+    #[test]
+    fn parse_acct() {
+        let expected = Acct::new("alice", "example.social");
+        for value in [
+            "acct:alice@example.social",
+            "@alice@example.social",
+            "alice@Example.Social",
+            " @alice@example.social ",
+        ] {
+            assert_eq!(value.parse::<Acct>().unwrap(), expected);
+        }
+        assert_eq!(expected.to_string(), "acct:alice@example.social");
+        assert_eq!(expected.handle(), "@alice@example.social");
+
+        for value in [
+            "alice",
+            "@alice@",
+            "@@example.social",
+            "a/b@example.social",
+            "alice@exa mple",
+        ] {
+            assert!(
+                matches!(value.parse::<Acct>(), Err(Error::Validation(_))),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn deserialize_jrd() {
+        let jrd: Jrd = serde_json::from_value(mastodon_jrd()).unwrap();
+        assert_eq!(jrd.actor_id(), Some("https://example.social/users/alice"));
+        assert_eq!(jrd.links.len(), 3);
+        assert_eq!(
+            jrd.links[2].template.as_deref(),
+            Some("https://example.social/authorize_interaction?uri={uri}")
+        );
+        assert_eq!(serde_json::to_value(&jrd).unwrap(), mastodon_jrd());
+
+        let ld_json: Jrd = serde_json::from_value(json!({
+          "subject": "acct:bob@example.com",
+          "links": [{
+            "rel": "self",
+            "type": "application/ld+json; profile=\"https://www.w3.org/ns/activitystreams\"",
+            "href": "https://example.com/bob"
+          }]
+        }))
+        .unwrap();
+        assert_eq!(ld_json.actor_id(), Some("https://example.com/bob"));
+    }
+
+    #[test]
+    fn build_request() {
+        let request = request(&Acct::new("alice", "example.social")).unwrap();
+        assert_eq!(
+            request.uri(),
+            "https://example.social/.well-known/webfinger?resource=acct%3Aalice%40example.social"
+        );
+        assert_eq!(request.headers()[header::ACCEPT], JRD_MEDIA_TYPE);
+        assert_eq!(
+            requested_resource(&request).unwrap(),
+            Acct::new("alice", "example.social")
+        );
+
+        let unencoded = Request::get(
+            "https://example.social/.well-known/webfinger?resource=acct:alice@example.social",
+        )
+        .body(())
+        .unwrap();
+        assert_eq!(requested_resource(&unencoded).unwrap().user, "alice");
+        let missing = Request::get("https://example.social/.well-known/webfinger")
+            .body(())
+            .unwrap();
+        assert!(requested_resource(&missing).is_err());
+    }
+
+    #[test]
+    fn respond_for_actor() {
+        let actor = ActorBuilder::default()
+            .with_base(|base| {
+                base.id(Some("https://example.social/users/alice".into()))
+                    .url(Some("https://example.social/@alice".into()))
+            })
+            .build()
+            .unwrap();
+        let jrd = Jrd::for_actor(&actor, &Acct::new("alice", "example.social")).unwrap();
+        assert_eq!(jrd.actor_id(), Some("https://example.social/users/alice"));
+        let mut expected = mastodon_jrd();
+        expected["links"].as_array_mut().unwrap().truncate(2);
+        expected["aliases"].as_array_mut().unwrap().reverse();
+        expected["links"].as_array_mut().unwrap().reverse();
+        assert_eq!(serde_json::to_value(&jrd).unwrap(), expected);
+
+        let found = response(Some(&jrd)).unwrap();
+        assert_eq!(found.headers()[header::CONTENT_TYPE], JRD_MEDIA_TYPE);
+        assert_eq!(response(None).unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    struct FakeServer(StatusCode, serde_json::Value);
+
+    #[async_trait]
+    impl HttpClient for FakeServer {
+        async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+            assert_eq!(request.uri().path(), WEBFINGER_PATH);
+            Ok(Response::builder()
+                .status(self.0)
+                .body(serde_json::to_vec(&self.1).unwrap())
+                .unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_actor() {
+        let acct = "@alice@example.social".parse().unwrap();
+        let server = FakeServer(StatusCode::OK, mastodon_jrd());
+        assert_eq!(
+            resolve(&server, &acct).await.unwrap(),
+            "https://example.social/users/alice"
+        );
+
+        let missing = FakeServer(StatusCode::NOT_FOUND, json!({}));
+        assert!(matches!(
+            resolve(&missing, &acct).await,
+            Err(Error::Http {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })
+        ));
+
+        let no_actor = FakeServer(
+            StatusCode::OK,
+            json!({ "subject": "acct:alice@example.social" }),
+        );
+        assert!(matches!(
+            resolve(&no_actor, &acct).await,
+            Err(Error::Validation(_))
+        ));
+    }
+}